@@ -0,0 +1,23 @@
+//! Serves the recorded API Responses, see `gold_pass_bot::mockapi`.
+//!
+//! `cargo run --example mock_api -- [fixture-dir] [bind-address]`, the Bot can then be pointed at
+//! it using `API_BASE_URL=http://127.0.0.1:8000`.
+
+use std::net::SocketAddr;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let mut args = std::env::args().skip(1);
+    let dir = args.next().unwrap_or_else(|| "fixtures/api".to_string());
+    let bind: SocketAddr = args
+        .next()
+        .map(|raw| raw.parse().expect("Invalid Bind-Address"))
+        .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], 8000)));
+
+    let addr = gold_pass_bot::mockapi::spawn(dir.clone(), bind);
+    tracing::info!("Serving {:?} on http://{}", dir, addr);
+
+    std::future::pending::<()>().await;
+}
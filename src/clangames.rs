@@ -0,0 +1,100 @@
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{ClanGamesConfig, Season};
+
+/// A single Clan Games Event, there is one Event in every Season
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClanGamesEvent {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// The maximum Number of Points a single Player can earn in the Event
+    pub max_points: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    /// The Event has not started yet, the Baseline still follows the Scores
+    Before,
+    Running,
+    Ended,
+}
+
+impl ClanGamesConfig {
+    /// The Event taking place in the given Season, `None` if the configured Days do not exist in
+    /// the Month
+    pub fn event(&self, season: &Season) -> Option<ClanGamesEvent> {
+        let at = |day: u32| {
+            Utc.with_ymd_and_hms(
+                season.year as i32,
+                season.month as u32,
+                day,
+                self.hour,
+                0,
+                0,
+            )
+            .single()
+        };
+
+        Some(ClanGamesEvent {
+            start: at(self.start_day)?,
+            end: at(self.end_day)?,
+            max_points: self.max_points,
+        })
+    }
+}
+
+impl ClanGamesEvent {
+    pub fn phase(&self, now: DateTime<Utc>) -> EventPhase {
+        if now < self.start {
+            EventPhase::Before
+        } else if now < self.end {
+            EventPhase::Running
+        } else {
+            EventPhase::Ended
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_phases() {
+        let event = ClanGamesConfig::default()
+            .event(&Season {
+                year: 2023,
+                month: 8,
+            })
+            .unwrap();
+
+        assert_eq!(
+            Utc.with_ymd_and_hms(2023, 8, 22, 8, 0, 0).unwrap(),
+            event.start
+        );
+        assert_eq!(
+            Utc.with_ymd_and_hms(2023, 8, 28, 8, 0, 0).unwrap(),
+            event.end
+        );
+        assert_eq!(4000, event.max_points);
+
+        assert_eq!(
+            EventPhase::Before,
+            event.phase(Utc.with_ymd_and_hms(2023, 8, 22, 7, 59, 0).unwrap())
+        );
+        assert_eq!(EventPhase::Running, event.phase(event.start));
+        assert_eq!(EventPhase::Ended, event.phase(event.end));
+
+        let invalid = ClanGamesConfig {
+            end_day: 31,
+            ..Default::default()
+        };
+        assert_eq!(
+            None,
+            invalid.event(&Season {
+                year: 2023,
+                month: 9
+            })
+        );
+    }
+}
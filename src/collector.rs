@@ -1,11 +1,13 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, sync::Mutex, time::Duration};
 
 use chrono::Datelike;
-use serde::Deserialize;
+use futures::{Stream, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::{
-    ClanStorage, ClanTag, CwlWarStats, MemberWarStats, PlayerGamesStats, PlayerTag, Season,
-    Storage, WarAttack, WarStats, WarTag,
+    ClanGamesEvent, ClanTag, CwlWarStats, EventPhase, MemberWarStats, MissingAttacks, OpenWar,
+    PlayerDonationStats, PlayerGamesStats, PlayerTag, RaidMember, RaidWeekendStats, Role, Season,
+    Storage, WarAttack, WarKind, WarStats, WarSummary, WarTag,
 };
 
 mod api;
@@ -14,11 +16,17 @@ mod warclient;
 pub use warclient::*;
 
 mod time;
-pub use time::Time;
+pub use time::{parse_timestamp, Time};
 
 mod cwl;
 pub use cwl::*;
 
+mod ratelimit;
+pub use ratelimit::{RateLimiter, RetryPolicy};
+
+mod endpoint;
+pub use endpoint::*;
+
 #[derive(Debug)]
 pub enum LoadError {
     ReqwestError(reqwest::Error),
@@ -26,10 +34,64 @@ pub enum LoadError {
     Deserialize(reqwest::Error),
 }
 
+impl LoadError {
+    /// Whether the Request may succeed if it is retried, which is the case for Timeouts, Rate
+    /// Limits and Server Errors
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ReqwestError(e) => e.is_timeout() || e.is_connect(),
+            Self::NotOkResponse(status) => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            Self::Deserialize(_) => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CollectError {
+    /// Loading Data from the API failed
+    Load(LoadError),
+    /// The API returned a Response that is missing required Data
+    MissingData(&'static str),
+}
+
+static REQUESTS: once_cell::sync::Lazy<prometheus::IntCounterVec> =
+    once_cell::sync::Lazy::new(|| {
+        let counter = prometheus::IntCounterVec::new(
+            prometheus::Opts::new("api_requests", "The Number of Requests sent to the API"),
+            &["endpoint", "status"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(counter.clone())).unwrap();
+        counter
+    });
+
+static REQUEST_DURATION: once_cell::sync::Lazy<prometheus::HistogramVec> =
+    once_cell::sync::Lazy::new(|| {
+        let histogram = prometheus::HistogramVec::new(
+            prometheus::HistogramOpts::new(
+                "api_request_duration_seconds",
+                "The Time it took to load an Endpoint, including Retries",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(histogram.clone())).unwrap();
+        histogram
+    });
+
+/// The URL of the official Clash of Clans API
+pub const DEFAULT_BASE_URL: &str = "https://api.clashofclans.com/v1";
+
 /// A Client for the Clash of Clans API
 pub struct Client {
     client: reqwest::Client,
     api_key: Cow<'static, str>,
+    /// The URL all the Paths are relative to, without a trailing `/`
+    base_url: String,
+    limiter: RateLimiter,
+    retries: RetryPolicy,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,8 +125,8 @@ pub struct PlayerInfo {
     expLevel: usize,
     trophies: usize,
     bestTrophies: usize,
-    donations: usize,
-    donationsReceived: usize,
+    pub donations: usize,
+    pub donationsReceived: usize,
     builderHallLevel: Option<usize>,
     builderBaseTrophies: usize,
     bestBuilderBaseTrophies: usize,
@@ -88,19 +150,33 @@ pub struct PlayerAchievement {
 #[derive(Debug, Deserialize)]
 pub struct WarLog {
     pub items: Vec<WarLogEntry>,
+    #[serde(default)]
+    pub paging: Paging,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WarLogEntry {
     #[serde(rename = "attacksPerMember")]
     attacks_per_member: usize,
-    clan: serde_json::Value,
+    pub clan: WarLogClan,
     #[serde(rename = "endTime")]
-    end_time: String,
-    opponent: serde_json::Value,
-    result: String,
+    pub end_time: String,
+    pub opponent: WarLogClan,
+    /// Not set for the Rounds of the Clan War League
+    pub result: Option<String>,
     #[serde(rename = "teamSize")]
-    team_size: usize,
+    pub team_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WarLogClan {
+    /// Not set for the Opponents in the Clan War League
+    pub tag: Option<ClanTag>,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub stars: usize,
+    #[serde(rename = "destructionPercentage", default)]
+    pub destruction_percentage: f32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -144,12 +220,12 @@ pub struct ClanMember {
     versusTrophies: Option<usize>,
     pub tag: PlayerTag,
     name: String,
-    role: serde_json::Value,
+    pub role: Role,
     expLevel: usize,
     clanRank: usize,
     previousClanRank: usize,
-    donations: usize,
-    donationsReceived: usize,
+    pub donations: usize,
+    pub donationsReceived: usize,
     trophies: usize,
     builderBaseTrophies: usize,
     playerHouse: Option<serde_json::Value>,
@@ -181,9 +257,18 @@ pub struct CapitalRaidWeekendMember {
     pub tag: PlayerTag,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyTokenResponse {
+    pub tag: PlayerTag,
+    pub token: String,
+    pub status: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CapitalRaidWeekendLogs {
     pub items: Vec<CapitalRaidWeekend>,
+    #[serde(default)]
+    pub paging: Paging,
 }
 
 impl Client {
@@ -192,9 +277,160 @@ impl Client {
         S: Into<Cow<'static, str>>,
     {
         Self {
-            client: reqwest::Client::new(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap(),
             api_key: api.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            limiter: RateLimiter::new(10.0, 20),
+            retries: RetryPolicy::default(),
+        }
+    }
+
+    /// Sends the Requests to the given URL instead of the official API, e.g. to use a Proxy
+    pub fn with_base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The Timeout for a single Request, Requests that time out are retried
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        self
+    }
+
+    pub fn with_rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: RetryPolicy) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sends the Request once the Rate-Limit allows it and retries it if it failed with a
+    /// retryable Error, only returns successful Responses
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, LoadError> {
+        let mut retry = 0;
+        loop {
+            let attempt = request
+                .try_clone()
+                .expect("Requests to the API do not have streaming Bodies");
+
+            self.limiter.acquire().await;
+            let (error, retry_after) = match attempt.send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| ratelimit::parse_retry_after(v, chrono::Utc::now()));
+                    (LoadError::NotOkResponse(resp.status()), retry_after)
+                }
+                Err(e) => (LoadError::ReqwestError(e), None),
+            };
+
+            if !error.is_retryable() {
+                return Err(error);
+            }
+            let delay = match self.retries.delay(retry, retry_after) {
+                Some(d) => d,
+                None => return Err(error),
+            };
+
+            tracing::warn!("Retrying Request in {:?}: {:?}", delay, error);
+            retry += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Loads the Response of the Endpoint
+    pub async fn request<E>(&self, endpoint: &E) -> Result<E::Response, LoadError>
+    where
+        E: Endpoint,
+    {
+        self.fetch(E::NAME, &endpoint.path(), endpoint.body()).await
+    }
+
+    /// Loads all the Items of the Endpoint, starting at the Page selected by the Endpoint and
+    /// following the `after` Cursors until the last Page
+    pub fn paginate<'c, E>(
+        &'c self,
+        endpoint: E,
+    ) -> impl Stream<Item = Result<E::Item, LoadError>> + 'c
+    where
+        E: PaginatedEndpoint + 'c,
+    {
+        futures::stream::try_unfold(Some(endpoint), move |endpoint| async move {
+            let endpoint = match endpoint {
+                Some(e) => e,
+                None => return Ok(None),
+            };
+
+            let (items, paging) = E::into_page(self.request(&endpoint).await?);
+            let next = match paging.cursors.after {
+                Some(cursor) if !items.is_empty() => Some(endpoint.after(cursor)),
+                _ => None,
+            };
+
+            Ok(Some((
+                futures::stream::iter(items.into_iter().map(Ok)),
+                next,
+            )))
+        })
+        .try_flatten()
+    }
+
+    /// Loads the Response of an Endpoint that is not covered by an [`Endpoint`], the Path is
+    /// relative to the Base-URL and Tags have to be encoded using [`encode_tag`]
+    pub async fn get<T>(&self, path: &str) -> Result<T, LoadError>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch("other", path, None).await
+    }
+
+    #[tracing::instrument(skip(self, body))]
+    async fn fetch<T>(
+        &self,
+        name: &'static str,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T, LoadError>
+    where
+        T: DeserializeOwned,
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let request = match body {
+            Some(body) => self.client.post(url).json(&body),
+            None => self.client.get(url),
+        };
+
+        let timer = REQUEST_DURATION.with_label_values(&[name]).start_timer();
+        let result = match self.send(request.bearer_auth(&self.api_key)).await {
+            Ok(resp) => resp.json().await.map_err(LoadError::Deserialize),
+            Err(e) => Err(e),
+        };
+        timer.observe_duration();
+
+        let status = match &result {
+            Ok(_) => "ok".to_string(),
+            Err(LoadError::NotOkResponse(status)) => status.as_u16().to_string(),
+            Err(LoadError::ReqwestError(_)) => "error".to_string(),
+            Err(LoadError::Deserialize(_)) => "invalid".to_string(),
+        };
+        REQUESTS.with_label_values(&[name, &status]).inc();
+
+        if let Err(e) = result.as_ref() {
+            tracing::debug!("Loading {:?}: {:?}", path, e);
         }
+        result
     }
 
     pub fn war(&self) -> WarClient<'_> {
@@ -205,166 +441,99 @@ impl Client {
         &self,
         clan_tag: &ClanTag,
     ) -> Result<ClanWarLeagueGroup, LoadError> {
-        let resp = self
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clans/%23{}/currentwar/leaguegroup",
-                clan_tag
-                    .0
-                    .as_str()
-                    .strip_prefix("#")
-                    .unwrap_or(clan_tag.0.as_str())
-            ))
-            .bearer_auth(&self.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        let status = resp.status();
-        if !status.is_success() {
-            return Err(LoadError::NotOkResponse(status));
-        }
-
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+        self.request(&GetLeagueGroup(clan_tag)).await
     }
 
     pub async fn clan_war_league_war(
         &self,
         war_tag: &WarTag,
     ) -> Result<ClanWarLeagueWar, LoadError> {
-        let resp = self
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clanwarleagues/wars/%23{}",
-                war_tag
-                    .0
-                    .as_str()
-                    .strip_prefix("#")
-                    .unwrap_or(war_tag.0.as_str())
-            ))
-            .bearer_auth(&self.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if !resp.status().is_success() {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
-
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+        self.request(&GetLeagueWar(war_tag)).await
     }
 
     pub async fn clan_info(&self, clan: &ClanTag) -> Result<ClanInfo, LoadError> {
-        let resp = self
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clans/%23{}",
-                clan.0.as_str().strip_prefix("#").unwrap_or(clan.0.as_str())
-            ))
-            .bearer_auth(&self.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if !resp.status().is_success() {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
-
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+        self.request(&GetClanInfo(clan)).await
     }
 
     pub async fn captial_raid_seasons(
         &self,
         clan: &ClanTag,
     ) -> Result<CapitalRaidWeekendLogs, LoadError> {
-        let resp = self
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clans/%23{}/capitalraidseasons?limit=5",
-                clan.0.as_str().strip_prefix("#").unwrap_or(clan.0.as_str())
-            ))
-            .bearer_auth(&self.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if !resp.status().is_success() {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
+        self.request(&GetRaidSeasons(clan, Page::first(5))).await
+    }
 
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+    /// All the Raid Weekends of the Clan, starting with the most recent one
+    pub fn all_raid_seasons<'c>(
+        &'c self,
+        clan: &'c ClanTag,
+    ) -> impl Stream<Item = Result<CapitalRaidWeekend, LoadError>> + 'c {
+        self.paginate(GetRaidSeasons(clan, Page::default()))
     }
 
     pub async fn player_info(&self, player: &PlayerTag) -> Result<PlayerInfo, LoadError> {
-        let resp = self
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/players/%23{}",
-                player
-                    .0
-                    .as_str()
-                    .strip_prefix("#")
-                    .unwrap_or(player.0.as_str())
-            ))
-            .bearer_auth(&self.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if !resp.status().is_success() {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
+        self.request(&GetPlayerInfo(player)).await
+    }
 
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+    /// Verifies that the given API-Token, found in the in-game settings, belongs to the Player
+    pub async fn verify_player_token(
+        &self,
+        player: &PlayerTag,
+        token: &str,
+    ) -> Result<bool, LoadError> {
+        let result = self.request(&VerifyPlayerToken(player, token)).await?;
+        Ok(result.status == "ok")
     }
 }
 
-#[tracing::instrument(skip(client, clan_season_stats))]
+/// Updates the Roster and the Names and Donations of the current Members
+#[tracing::instrument(skip(client, storage))]
 pub async fn update_names(
     client: &Client,
     clan: &ClanTag,
-    clan_season_stats: &mut ClanStorage,
-) -> Result<(), ()> {
+    storage: &Mutex<Storage>,
+) -> Result<(), CollectError> {
     let info = match client.clan_info(clan).await {
         Ok(i) => i,
         Err(e) => {
             tracing::error!("Failed to load Clan Information {:?}", e);
-            return Err(());
+            return Err(CollectError::Load(e));
         }
     };
 
-    clan_season_stats.player_names.clear();
+    let season = Season::current();
+
+    let mut storage = storage.lock().unwrap();
+    let (previous_donations, previous_members) = storage
+        .get(clan, &season.previous())
+        .map(|s| (s.donations.clone(), s.roster.members.clone()))
+        .unwrap_or_default();
+    let clan_season_stats = storage
+        .get_mut(clan, &season)
+        .ok_or(CollectError::MissingData("Clan Season"))?;
+
+    // The Roster of a new Season continues from the Members at the end of the previous one
+    if clan_season_stats.roster.members.is_empty() {
+        clan_season_stats.roster.members = previous_members;
+    }
+    clan_season_stats.roster.update(
+        info.memberList
+            .iter()
+            .map(|m| (m.tag.clone(), m.role))
+            .collect(),
+        chrono::Utc::now(),
+    );
+
+    // The Names of former Members are kept to still show their Stats
     for member in info.memberList {
+        let donations = clan_season_stats
+            .donations
+            .entry(member.tag.clone())
+            .or_insert_with(|| match previous_donations.get(&member.tag) {
+                Some(previous) => PlayerDonationStats::continue_from(previous),
+                None => PlayerDonationStats::new(member.donations, member.donationsReceived),
+            });
+        donations.update(member.donations, member.donationsReceived);
+
         clan_season_stats
             .player_names
             .insert(member.tag, member.name);
@@ -373,29 +542,34 @@ pub async fn update_names(
     Ok(())
 }
 
+/// Updates the Stats of the current Clan War League, returns the Round that is currently in War
 #[tracing::instrument(skip(client, storage))]
-pub async fn update_cwl(client: &Client, clan: &ClanTag, storage: &mut Storage) -> Result<(), ()> {
+pub async fn update_cwl(
+    client: &Client,
+    clan: &ClanTag,
+    storage: &Mutex<Storage>,
+) -> Result<Vec<OpenWar>, CollectError> {
     let w = match client.clan_war_league_group(clan).await {
         Ok(w) => w,
         Err(LoadError::NotOkResponse(resp)) if resp == reqwest::StatusCode::NOT_FOUND => {
             let now = chrono::Utc::now();
             if now.day() > 10 {
                 tracing::debug!("Ignoring CWL Not found");
-                return Ok(());
+                return Ok(Vec::new());
             } else {
                 tracing::error!("Loading Clan War League Group - NotOk: {:?}", resp);
-                return Err(());
+                return Err(CollectError::Load(LoadError::NotOkResponse(resp)));
             }
         }
         Err(e) => {
             tracing::error!("Loading Clan War League Group: {:?}", e);
-            return Err(());
+            return Err(CollectError::Load(e));
         }
     };
 
     let war_season: Season = w.season.into();
-    let clan_season_stats = storage.get_mut(clan, &war_season).unwrap();
 
+    let mut wars = Vec::new();
     for (round_index, round) in w.rounds.iter().enumerate() {
         for wtag in round.war_tags.iter() {
             if wtag.0.as_str() == "#0" {
@@ -408,75 +582,115 @@ pub async fn update_cwl(client: &Client, clan: &ClanTag, storage: &mut Storage)
                     continue;
                 }
 
-                let clan = if &w.clan.tag == clan {
-                    w.clan
-                } else {
-                    w.opponent
-                };
-
-                if clan_season_stats.cwl.wars.len() <= round_index {
-                    clan_season_stats.cwl.wars.extend(
-                        (0..(clan_season_stats.cwl.wars.len() - round_index) + 1).map(|_| {
-                            CwlWarStats {
-                                members: HashMap::new(),
-                            }
-                        }),
-                    );
-                }
-                let cwl_stats = clan_season_stats.cwl.wars.get_mut(round_index).expect("");
-
-                for member in clan.members.iter() {
-                    let member_stats =
-                        cwl_stats
-                            .members
-                            .entry(member.tag.clone())
-                            .or_insert_with(|| MemberWarStats {
-                                attacks: Vec::new(),
-                            });
-
-                    if let Some(attacks) = &member.attacks {
-                        member_stats.attacks = attacks
-                            .iter()
-                            .map(|raw_attack| WarAttack {
-                                destruction: raw_attack.destructionPercentage,
-                                stars: raw_attack.stars,
-                                duration: raw_attack.duration,
-                            })
-                            .collect();
-                    }
-                }
+                wars.push((round_index, w));
             }
         }
     }
 
-    Ok(())
+    let mut storage = storage.lock().unwrap();
+    let clan_season_stats = storage.get_mut(clan, &war_season).unwrap();
+
+    let mut open_wars = Vec::new();
+    for (round_index, w) in wars {
+        let clan_tag = clan;
+        let clan = if &w.clan.tag == clan {
+            w.clan
+        } else {
+            w.opponent
+        };
+
+        if matches!(w.state, ClanWarLeagueWarState::War) {
+            match parse_timestamp(&w.endTime) {
+                Some(end_time) => open_wars.push(OpenWar {
+                    clan: clan_tag.clone(),
+                    kind: WarKind::Cwl { round: round_index },
+                    end_time,
+                    missing: clan
+                        .members
+                        .iter()
+                        .filter(|m| m.attacks.as_ref().map(|a| a.is_empty()).unwrap_or(true))
+                        .map(|m| MissingAttacks {
+                            tag: m.tag.clone(),
+                            name: m.name.clone(),
+                            remaining: 1,
+                        })
+                        .collect(),
+                }),
+                None => tracing::error!("Invalid End-Time {:?}", w.endTime),
+            };
+        }
+
+        if clan_season_stats.cwl.wars.len() <= round_index {
+            clan_season_stats.cwl.wars.extend(
+                (0..(clan_season_stats.cwl.wars.len() - round_index) + 1).map(|_| CwlWarStats {
+                    members: HashMap::new(),
+                }),
+            );
+        }
+        let cwl_stats = clan_season_stats.cwl.wars.get_mut(round_index).expect("");
+
+        for member in clan.members.iter() {
+            let member_stats = cwl_stats
+                .members
+                .entry(member.tag.clone())
+                .or_insert_with(|| MemberWarStats {
+                    attacks: Vec::new(),
+                });
+
+            if let Some(attacks) = &member.attacks {
+                member_stats.attacks = attacks
+                    .iter()
+                    .map(|raw_attack| WarAttack {
+                        destruction: raw_attack.destructionPercentage,
+                        stars: raw_attack.stars,
+                        duration: raw_attack.duration,
+                        order: Some(raw_attack.order),
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    Ok(open_wars)
 }
 
+/// Updates the Stats of the current regular War, returns the War if it is currently running.
+///
+/// Wars are recorded from the start of their preparation and updated until their final state is
+/// observed once they ended, after which they are not modified anymore.
 #[tracing::instrument(skip(client, storage))]
 pub async fn update_war(
     client: &Client,
     clan_tag: &ClanTag,
-    storage: &mut Storage,
-) -> Result<(), ()> {
+    storage: &Mutex<Storage>,
+) -> Result<Option<OpenWar>, CollectError> {
     let war = match client.war().current(&clan_tag).await {
         Ok(w) => w,
         Err(e) => {
             tracing::error!("Error loading War: {:?}", e);
-            return Err(());
+            return Err(CollectError::Load(e));
         }
     };
 
-    if !matches!(war.state, CurrentWarState::InWar) {
-        tracing::info!("WAR: Not in War currently {:?}", war.state);
-        return Ok(());
-    }
+    let finished = match war.state {
+        CurrentWarState::Preparation | CurrentWarState::InWar => false,
+        CurrentWarState::Ended => true,
+        other => {
+            tracing::info!("WAR: Not in War currently {:?}", other);
+            return Ok(None);
+        }
+    };
+    let in_war = matches!(war.state, CurrentWarState::InWar);
+
+    let attacks_per_member = war.attacks_per_member.unwrap_or(2);
+    let end_time = war.end_time.as_deref().and_then(parse_timestamp);
 
     let clan = war.clan;
     let members = match clan.members {
         Some(m) => m,
         None => {
             tracing::error!("Current War Clan is missing Members");
-            return Err(());
+            return Err(CollectError::MissingData("War Clan Members"));
         }
     };
 
@@ -484,18 +698,41 @@ pub async fn update_war(
         Some(t) => t,
         None => {
             tracing::error!("Current War missing Start Time");
-            return Err(());
+            return Err(CollectError::MissingData("War Start Time"));
         }
     };
 
     let season: Season = start_time.clone().into();
 
+    let mut storage = storage.lock().unwrap();
     let clan_season_stats = storage.get_mut(clan_tag, &season).unwrap();
 
-    // clan_season_stats.wars.insert(start_time, WarStats {});
+    if clan_season_stats
+        .wars
+        .get(&start_time)
+        .map(|w| w.finished)
+        .unwrap_or(false)
+    {
+        tracing::debug!("War starting {} is already recorded", start_time);
+        return Ok(None);
+    }
+
+    let missing = members
+        .iter()
+        .filter(|m| m.attacks.len() < attacks_per_member)
+        .map(|m| MissingAttacks {
+            tag: m.tag.clone(),
+            name: m.name.clone(),
+            remaining: attacks_per_member - m.attacks.len(),
+        })
+        .collect();
 
     let war_stats = WarStats {
         start_time: start_time.clone(),
+        end_time: end_time.map(Time::from),
+        finished,
+        summary: None,
+        summary_only: false,
         members: members
             .into_iter()
             .filter_map(|member| {
@@ -506,6 +743,7 @@ pub async fn update_war(
                         destruction: rattack.destructionPercentage,
                         stars: rattack.stars,
                         duration: rattack.duration,
+                        order: Some(rattack.order),
                     })
                     .collect();
 
@@ -518,33 +756,253 @@ pub async fn update_war(
             })
             .collect(),
     };
+    if finished {
+        tracing::info!("Recording final State of War starting {}", start_time);
+    }
     clan_season_stats.wars.insert(start_time, war_stats);
 
+    if !in_war {
+        return Ok(None);
+    }
+
+    let end_time = match end_time {
+        Some(t) => t,
+        None => {
+            tracing::error!("Current War missing End Time");
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(OpenWar {
+        clan: clan_tag.clone(),
+        kind: WarKind::Regular,
+        end_time,
+        missing,
+    }))
+}
+
+/// Reconciles the recorded Wars with the War-Log of the Clan.
+///
+/// Wars in the War-Log have ended, so any recorded War with the same End-Time is marked as
+/// finished, even if its final state was never observed. The War-Log does not contain the
+/// Attacks of the Members, so Wars that are missing entirely are recorded with only their
+/// Summary.
+#[tracing::instrument(skip(client, storage))]
+pub async fn reconcile_wars(
+    client: &Client,
+    clan_tag: &ClanTag,
+    storage: &Mutex<Storage>,
+) -> Result<(), CollectError> {
+    let logs = match client.war().logs(clan_tag).await {
+        Ok(l) => l,
+        Err(LoadError::NotOkResponse(status)) if status == reqwest::StatusCode::FORBIDDEN => {
+            tracing::debug!("War-Log is private");
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Loading War-Log: {:?}", e);
+            return Err(CollectError::Load(e));
+        }
+    };
+
+    let mut storage = storage.lock().unwrap();
+    for entry in logs.items {
+        record_war_log_entry(&mut storage, clan_tag, &entry)?;
+    }
+
     Ok(())
 }
 
+/// How an Entry of the War-Log was recorded
+#[derive(Debug, PartialEq)]
+enum RecordedEntry {
+    /// The War was never recorded and is now recorded from the War-Log, without any Attacks
+    Added,
+    /// The recorded War was missing the Summary from the War-Log
+    Summarized,
+    Unchanged,
+    /// The Entry can not be recorded, like CWL Rounds
+    Skipped,
+}
+
+/// Records the Result of a War from the War-Log, Wars that were never recorded are added with
+/// only the Summary
+fn record_war_log_entry(
+    storage: &mut Storage,
+    clan_tag: &ClanTag,
+    entry: &WarLogEntry,
+) -> Result<RecordedEntry, CollectError> {
+    let end = match parse_timestamp(&entry.end_time) {
+        Some(t) => t,
+        None => {
+            tracing::error!("Invalid End-Time {:?}", entry.end_time);
+            return Ok(RecordedEntry::Skipped);
+        }
+    };
+    if entry.opponent.tag.is_none() {
+        tracing::debug!("Skipping CWL Round ending {}", entry.end_time);
+        return Ok(RecordedEntry::Skipped);
+    }
+
+    // The Battle-Day of a War lasts one day
+    let end_time = Time::from(end);
+    let start_time = Time::from(end - chrono::Duration::days(1));
+
+    let recorded = storage
+        .clan_seasons_mut(clan_tag)
+        .flat_map(|(_, stats)| stats.wars.values_mut())
+        .find(|war| war.end_time == Some(end_time) || war.start_time == start_time);
+    match recorded {
+        Some(war) if !war.finished => {
+            tracing::info!(
+                "War ending {} ended between updates, Attacks made after the last update are missing",
+                end_time
+            );
+            war.finished = true;
+            war.summary = Some(war_summary(entry));
+            Ok(RecordedEntry::Summarized)
+        }
+        Some(war) if war.summary.is_none() => {
+            war.summary = Some(war_summary(entry));
+            Ok(RecordedEntry::Summarized)
+        }
+        Some(_) => Ok(RecordedEntry::Unchanged),
+        None => {
+            tracing::info!("Recording War ending {} from the War-Log", end_time);
+            storage
+                .get_mut(clan_tag, &start_time.into())
+                .ok_or(CollectError::MissingData("Clan"))?
+                .wars
+                .insert(
+                    start_time,
+                    WarStats {
+                        start_time,
+                        end_time: Some(end_time),
+                        finished: true,
+                        members: HashMap::new(),
+                        summary: Some(war_summary(entry)),
+                        summary_only: true,
+                    },
+                );
+            Ok(RecordedEntry::Added)
+        }
+    }
+}
+
+fn war_summary(entry: &WarLogEntry) -> WarSummary {
+    WarSummary {
+        result: entry.result.clone(),
+        team_size: entry.team_size,
+        stars: entry.clan.stars,
+        destruction: entry.clan.destruction_percentage,
+        opponent: entry.opponent.tag.clone(),
+    }
+}
+
+/// The Changes made by a Backfill
+#[derive(Debug, Default, PartialEq)]
+pub struct BackfillReport {
+    /// Wars that were missing and are now recorded from the War-Log, without any Attacks
+    pub wars_added: usize,
+    /// Recorded Wars that were missing the Summary from the War-Log
+    pub wars_summarized: usize,
+    pub raids_added: usize,
+    /// Entries that could not be recorded, like Raid Weekends without a Member list
+    pub skipped: usize,
+}
+
+/// Fills in the Wars and Raid Weekends of past Seasons from the full War-Log and Raid history of
+/// the Clan, the already recorded Stats are never overwritten.
+///
+/// The War-Log only contains the Outcome of a War, so the Wars added from it are marked as
+/// [`WarStats::summary_only`]. The Raid history contains the Loot of every Member, so the Raid
+/// Weekends are complete.
+#[tracing::instrument(skip(client, storage))]
+pub async fn backfill(
+    client: &Client,
+    clan_tag: &ClanTag,
+    storage: &Mutex<Storage>,
+) -> Result<BackfillReport, CollectError> {
+    let war_log: Vec<WarLogEntry> = match client.war().all_logs(clan_tag).try_collect().await {
+        Ok(l) => l,
+        Err(LoadError::NotOkResponse(status)) if status == reqwest::StatusCode::FORBIDDEN => {
+            tracing::warn!("War-Log is private, only backfilling Raid Weekends");
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::error!("Loading War-Log: {:?}", e);
+            return Err(CollectError::Load(e));
+        }
+    };
+    let raids: Vec<CapitalRaidWeekend> = match client.all_raid_seasons(clan_tag).try_collect().await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("Loading Capital Raid Seasons: {:?}", e);
+            return Err(CollectError::Load(e));
+        }
+    };
+
+    let mut report = BackfillReport::default();
+    let mut storage = storage.lock().unwrap();
+
+    for entry in war_log {
+        match record_war_log_entry(&mut storage, clan_tag, &entry)? {
+            RecordedEntry::Added => report.wars_added += 1,
+            RecordedEntry::Summarized => report.wars_summarized += 1,
+            RecordedEntry::Unchanged => {}
+            RecordedEntry::Skipped => report.skipped += 1,
+        }
+    }
+
+    for raid in raids {
+        let stats = match raid_weekend_stats(raid) {
+            Some(s) => s,
+            None => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+
+        let season = storage
+            .get_mut(clan_tag, &stats.start_time.into())
+            .ok_or(CollectError::MissingData("Clan"))?;
+        if let std::collections::btree_map::Entry::Vacant(entry) =
+            season.raid_weekend.entry(stats.start_time)
+        {
+            tracing::info!("Backfilling Raid Weekend starting {}", stats.start_time);
+            entry.insert(stats);
+            report.raids_added += 1;
+        }
+    }
+
+    Ok(report)
+}
+
 #[tracing::instrument(skip(client, storage))]
 pub async fn update_clan_games(
     client: &Client,
     clan_tag: &ClanTag,
-    storage: &mut Storage,
-) -> Result<(), ()> {
+    storage: &Mutex<Storage>,
+    event: &ClanGamesEvent,
+) -> Result<(), CollectError> {
+    let phase = event.phase(chrono::Utc::now());
+    if phase == EventPhase::Ended {
+        tracing::debug!("Clan Games already ended");
+        return Ok(());
+    }
+
     let clan = match client.clan_info(&clan_tag).await {
         Ok(clan) => clan,
         Err(e) => {
             tracing::error!("Loading Clan Information: {:?}", e);
-            return Err(());
+            return Err(CollectError::Load(e));
         }
     };
 
-    let season = Season::current();
-
-    let clan_stats = storage.get_mut(clan_tag, &season).unwrap();
-
+    let mut scores = Vec::new();
     for member in clan.memberList {
-        let player_tag = member.tag;
-
-        let player_info = match client.player_info(&player_tag).await {
+        let player_info = match client.player_info(&member.tag).await {
             Ok(pi) => pi,
             Err(e) => {
                 tracing::error!("Loading Player Info: {:?}", e);
@@ -560,22 +1018,82 @@ pub async fn update_clan_games(
                     achievement.value
                 );
 
-                let player_entry =
-                    clan_stats
-                        .games
-                        .entry(player_tag.clone())
-                        .or_insert(PlayerGamesStats {
-                            start_score: Some(achievement.value),
-                            end_score: achievement.value,
-                        });
-                if player_entry.start_score.is_none() {
-                    player_entry.start_score = Some(achievement.value);
-                }
-
-                player_entry.end_score = achievement.value;
+                scores.push((member.tag.clone(), achievement.value));
             }
         }
     }
 
+    let season: Season = Time::from(event.start).into();
+
+    let mut storage = storage.lock().unwrap();
+    let clan_stats = storage.get_mut(clan_tag, &season).unwrap();
+
+    for (player_tag, score) in scores {
+        clan_stats
+            .games
+            .entry(player_tag)
+            .or_insert_with(|| PlayerGamesStats::new(score, phase))
+            .update(score, phase, event.max_points);
+    }
+
     Ok(())
 }
+
+/// Updates the Stats of the most recent Raid Weekends
+#[tracing::instrument(skip(client, storage))]
+pub async fn update_raids(
+    client: &Client,
+    clan_tag: &ClanTag,
+    storage: &Mutex<Storage>,
+) -> Result<(), CollectError> {
+    let raid_res = match client.captial_raid_seasons(clan_tag).await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("Error loading Capital Raid Seasons: {:?}", e);
+            return Err(CollectError::Load(e));
+        }
+    };
+
+    let mut storage = storage.lock().unwrap();
+    for raid in raid_res.items {
+        tracing::debug!("Start-Time: {:?}", raid.startTime);
+        let stats = match raid_weekend_stats(raid) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let clan_season_stats = storage.get_mut(clan_tag, &stats.start_time.into()).unwrap();
+
+        clan_season_stats
+            .raid_weekend
+            .insert(stats.start_time, stats);
+    }
+
+    Ok(())
+}
+
+fn raid_weekend_stats(raid: CapitalRaidWeekend) -> Option<RaidWeekendStats> {
+    let members = match raid.members {
+        Some(m) => m
+            .into_iter()
+            .map(|member| {
+                (
+                    member.tag,
+                    RaidMember {
+                        looted: member.capitalResourcesLooted,
+                    },
+                )
+            })
+            .collect(),
+        None => {
+            tracing::trace!("Skipping weekend, because there is no member list");
+
+            return None;
+        }
+    };
+
+    Some(RaidWeekendStats {
+        start_time: raid.startTime,
+        members,
+    })
+}
@@ -64,7 +64,7 @@ pub struct ClanWarLeagueWar {
     teamSize: usize,
     preparationStartTime: String,
     startTime: String,
-    endTime: String,
+    pub endTime: String,
     pub clan: ClanWarLeagueWarClan,
     pub opponent: ClanWarLeagueWarClan,
     warStartTime: String,
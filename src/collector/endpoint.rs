@@ -0,0 +1,296 @@
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    CapitalRaidWeekend, CapitalRaidWeekendLogs, ClanInfo, ClanTag, ClanWarLeagueGroup,
+    ClanWarLeagueWar, CurrentWar, PlayerInfo, PlayerTag, VerifyTokenResponse, WarLog, WarLogEntry,
+    WarTag,
+};
+
+/// An Endpoint of the API that is loaded using a GET-Request, or a POST-Request if it has a Body
+pub trait Endpoint {
+    /// The Response returned by the Endpoint
+    type Response: DeserializeOwned;
+
+    /// The Name of the Endpoint used in Metrics and Traces, should not contain any Tags
+    const NAME: &'static str;
+
+    /// The Path of the Endpoint relative to the Base-URL, including the Query
+    fn path(&self) -> String;
+
+    /// The JSON-Body sent with the Request
+    fn body(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// An Endpoint returning a List of Items that is split into Pages
+pub trait PaginatedEndpoint: Endpoint {
+    type Item;
+
+    /// The same Endpoint for the Page following the given Cursor, keeps the Limit
+    fn after(&self, cursor: String) -> Self;
+
+    /// Splits the Response into the Items and the Cursors to the neighbouring Pages
+    fn into_page(response: Self::Response) -> (Vec<Self::Item>, Paging);
+}
+
+/// Selects a Page of a paginated Endpoint, the API returns the first Page if no Cursor is set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    /// The maximum Number of Items in the Page
+    pub limit: Option<usize>,
+    /// Return the Items after this Cursor
+    pub after: Option<String>,
+    /// Return the Items before this Cursor
+    pub before: Option<String>,
+}
+
+/// The Paging Information returned by paginated Endpoints
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Paging {
+    #[serde(default)]
+    pub cursors: Cursors,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Cursors {
+    /// The Cursor to the next Page, `None` on the last Page
+    pub after: Option<String>,
+    /// The Cursor to the previous Page, `None` on the first Page
+    pub before: Option<String>,
+}
+
+/// Encodes a Tag for use in a Path, the leading `#` has to be percent-encoded
+pub fn encode_tag(tag: &str) -> String {
+    format!("%23{}", tag.strip_prefix('#').unwrap_or(tag))
+}
+
+/// Percent-Encodes a Value for use in the Query
+fn encode_query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            other => format!("%{:02X}", other),
+        })
+        .collect()
+}
+
+impl Page {
+    /// The first Page with at most `limit` Items
+    pub fn first(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    /// The Query selecting the Page, including the leading `?` if it is not empty
+    pub fn query(&self) -> String {
+        let params: Vec<String> = [
+            self.limit.map(|l| format!("limit={}", l)),
+            self.after
+                .as_ref()
+                .map(|a| format!("after={}", encode_query(a))),
+            self.before
+                .as_ref()
+                .map(|b| format!("before={}", encode_query(b))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
+pub struct GetClanInfo<'t>(pub &'t ClanTag);
+
+pub struct GetCurrentWar<'t>(pub &'t ClanTag);
+
+pub struct GetWarLog<'t>(pub &'t ClanTag, pub Page);
+
+pub struct GetLeagueGroup<'t>(pub &'t ClanTag);
+
+pub struct GetLeagueWar<'t>(pub &'t WarTag);
+
+pub struct GetRaidSeasons<'t>(pub &'t ClanTag, pub Page);
+
+pub struct GetPlayerInfo<'t>(pub &'t PlayerTag);
+
+/// Verifies the API-Token found in the in-game Settings of the Player
+pub struct VerifyPlayerToken<'t>(pub &'t PlayerTag, pub &'t str);
+
+impl Endpoint for GetClanInfo<'_> {
+    type Response = ClanInfo;
+    const NAME: &'static str = "clan";
+
+    fn path(&self) -> String {
+        format!("/clans/{}", encode_tag(&self.0 .0))
+    }
+}
+
+impl Endpoint for GetCurrentWar<'_> {
+    type Response = CurrentWar;
+    const NAME: &'static str = "currentwar";
+
+    fn path(&self) -> String {
+        format!("/clans/{}/currentwar", encode_tag(&self.0 .0))
+    }
+}
+
+impl Endpoint for GetWarLog<'_> {
+    type Response = WarLog;
+    const NAME: &'static str = "warlog";
+
+    fn path(&self) -> String {
+        format!("/clans/{}/warlog{}", encode_tag(&self.0 .0), self.1.query())
+    }
+}
+
+impl PaginatedEndpoint for GetWarLog<'_> {
+    type Item = WarLogEntry;
+
+    fn after(&self, cursor: String) -> Self {
+        Self(
+            self.0,
+            Page {
+                limit: self.1.limit,
+                after: Some(cursor),
+                before: None,
+            },
+        )
+    }
+
+    fn into_page(response: Self::Response) -> (Vec<Self::Item>, Paging) {
+        (response.items, response.paging)
+    }
+}
+
+impl Endpoint for GetLeagueGroup<'_> {
+    type Response = ClanWarLeagueGroup;
+    const NAME: &'static str = "leaguegroup";
+
+    fn path(&self) -> String {
+        format!("/clans/{}/currentwar/leaguegroup", encode_tag(&self.0 .0))
+    }
+}
+
+impl Endpoint for GetLeagueWar<'_> {
+    type Response = ClanWarLeagueWar;
+    const NAME: &'static str = "leaguewar";
+
+    fn path(&self) -> String {
+        format!("/clanwarleagues/wars/{}", encode_tag(&self.0 .0))
+    }
+}
+
+impl Endpoint for GetRaidSeasons<'_> {
+    type Response = CapitalRaidWeekendLogs;
+    const NAME: &'static str = "capitalraidseasons";
+
+    fn path(&self) -> String {
+        format!(
+            "/clans/{}/capitalraidseasons{}",
+            encode_tag(&self.0 .0),
+            self.1.query()
+        )
+    }
+}
+
+impl PaginatedEndpoint for GetRaidSeasons<'_> {
+    type Item = CapitalRaidWeekend;
+
+    fn after(&self, cursor: String) -> Self {
+        Self(
+            self.0,
+            Page {
+                limit: self.1.limit,
+                after: Some(cursor),
+                before: None,
+            },
+        )
+    }
+
+    fn into_page(response: Self::Response) -> (Vec<Self::Item>, Paging) {
+        (response.items, response.paging)
+    }
+}
+
+impl Endpoint for GetPlayerInfo<'_> {
+    type Response = PlayerInfo;
+    const NAME: &'static str = "player";
+
+    fn path(&self) -> String {
+        format!("/players/{}", encode_tag(&self.0 .0))
+    }
+}
+
+impl Endpoint for VerifyPlayerToken<'_> {
+    type Response = VerifyTokenResponse;
+    const NAME: &'static str = "verifytoken";
+
+    fn path(&self) -> String {
+        format!("/players/{}/verifytoken", encode_tag(&self.0 .0))
+    }
+
+    fn body(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "token": self.1 }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let clan = ClanTag("#2L99VLJ9P".to_string());
+
+        assert_eq!("/clans/%232L99VLJ9P", GetClanInfo(&clan).path());
+        assert_eq!(
+            "/clans/%232L99VLJ9P/warlog?limit=10",
+            GetWarLog(&clan, Page::first(10)).path()
+        );
+        assert_eq!(
+            "/clans/%232L99VLJ9P/capitalraidseasons",
+            GetRaidSeasons(&clan, Page::default()).path()
+        );
+        assert_eq!(
+            "/clanwarleagues/wars/%238QU8J9LP",
+            GetLeagueWar(&WarTag("#8QU8J9LP".to_string())).path()
+        );
+        assert_eq!("%23P2Y8QJ0L", encode_tag("P2Y8QJ0L"));
+
+        let player = PlayerTag("#P2Y8QJ0L".to_string());
+        let verify = VerifyPlayerToken(&player, "abc123");
+        assert_eq!("/players/%23P2Y8QJ0L/verifytoken", verify.path());
+        assert_eq!(
+            Some(serde_json::json!({ "token": "abc123" })),
+            verify.body()
+        );
+        assert_eq!(None, GetPlayerInfo(&player).body());
+    }
+
+    #[test]
+    fn page_queries() {
+        let clan = ClanTag("#2L99VLJ9P".to_string());
+        let page = GetWarLog(&clan, Page::first(5)).after("eyJwb3MiOjV9+/=".to_string());
+
+        assert_eq!("?limit=5&after=eyJwb3MiOjV9%2B%2F%3D", page.1.query());
+        assert_eq!(
+            "?before=abc",
+            Page {
+                before: Some("abc".to_string()),
+                ..Default::default()
+            }
+            .query()
+        );
+    }
+}
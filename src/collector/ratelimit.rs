@@ -0,0 +1,189 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A Token-Bucket limiting the Number of Requests sent to the API
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// The Number of Tokens added per second
+    rate: f64,
+    /// The maximum Number of Tokens, allows short bursts of Requests
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Controls how often and how fast failed Requests are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The Number of Retries after the initial Request
+    pub max_retries: u32,
+    /// The Delay before the first Retry, doubled for every following Retry
+    pub base_delay: Duration,
+    /// The maximum Delay between two Attempts
+    pub max_delay: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate: requests_per_sec,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a Token if one is available, otherwise returns the Time until the next one is
+    fn try_acquire(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Waits until a Request may be sent
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire(Instant::now()) {
+            tracing::trace!("Rate limited for {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The Delay before the given 0-based Retry, `None` if the Request should not be retried
+    /// anymore. Never retries before the Delay requested by the API using `Retry-After`.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.min(16)))
+            .min(self.max_delay);
+
+        match retry_after {
+            Some(requested) if requested > self.max_delay => None,
+            Some(requested) => Some(requested.max(backoff)),
+            None => Some(backoff),
+        }
+    }
+}
+
+/// Parses the `Retry-After` Header, which contains either a Number of seconds or a Date
+pub(crate) fn parse_retry_after(
+    value: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let limiter = RateLimiter::new(2.0, 2);
+        let start = Instant::now();
+
+        assert_eq!(Ok(()), limiter.try_acquire(start));
+        assert_eq!(Ok(()), limiter.try_acquire(start));
+        assert_eq!(Err(Duration::from_millis(500)), limiter.try_acquire(start));
+
+        // Refills with the configured Rate, but never above the Capacity
+        assert_eq!(
+            Ok(()),
+            limiter.try_acquire(start + Duration::from_millis(500))
+        );
+        assert!(limiter
+            .try_acquire(start + Duration::from_millis(500))
+            .is_err());
+        let later = start + Duration::from_secs(60);
+        assert_eq!(Ok(()), limiter.try_acquire(later));
+        assert_eq!(Ok(()), limiter.try_acquire(later));
+        assert!(limiter.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn retry_delays() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+        };
+
+        assert_eq!(Some(Duration::from_secs(1)), policy.delay(0, None));
+        assert_eq!(Some(Duration::from_secs(4)), policy.delay(2, None));
+        assert_eq!(None, policy.delay(3, None));
+
+        // Retry-After is respected, unless it exceeds the maximum Delay
+        assert_eq!(
+            Some(Duration::from_secs(5)),
+            policy.delay(0, Some(Duration::from_secs(5)))
+        );
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            policy.delay(1, Some(Duration::from_secs(1)))
+        );
+        assert_eq!(None, policy.delay(0, Some(Duration::from_secs(30))));
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = chrono::Utc
+            .with_ymd_and_hms(2015, 10, 21, 7, 27, 0)
+            .unwrap();
+
+        assert_eq!(
+            Some(Duration::from_secs(120)),
+            parse_retry_after("120", now)
+        );
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now)
+        );
+        assert_eq!(None, parse_retry_after("soon", now));
+    }
+}
@@ -43,6 +43,33 @@ impl Serialize for Time {
     }
 }
 
+impl std::fmt::Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Parses a full Timestamp returned by the API, like `20230804T070000.000Z`
+pub fn parse_timestamp(raw: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+
+    chrono::NaiveDateTime::parse_from_str(raw, "%Y%m%dT%H%M%S%.fZ")
+        .ok()
+        .map(|t| chrono::Utc.from_utc_datetime(&t))
+}
+
+impl From<chrono::DateTime<chrono::Utc>> for Time {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
+        use chrono::Datelike;
+
+        Self {
+            year: value.year() as usize,
+            month: value.month() as usize,
+            day: value.day() as usize,
+        }
+    }
+}
+
 impl PartialOrd for Time {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         (self.year, self.month, self.day).partial_cmp(&(other.year, other.month, other.day))
@@ -79,6 +106,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn timestamp() {
+        let parsed = parse_timestamp("20230804T071530.000Z").unwrap();
+        assert_eq!("2023-08-04 07:15:30 UTC", parsed.to_string());
+        assert_eq!(
+            Time {
+                year: 2023,
+                month: 8,
+                day: 4
+            },
+            Time::from(parsed)
+        );
+
+        assert!(parse_timestamp("2023-08-04").is_none());
+    }
+
     #[test]
     fn ordering() {
         let first = Time {
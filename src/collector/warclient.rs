@@ -1,7 +1,10 @@
-use reqwest::StatusCode;
+use futures::Stream;
 use serde::Deserialize;
 
-use crate::{ClanBadges, ClanTag, Client, LoadError, PlayerGamesStats, PlayerTag, Time, WarLog};
+use crate::{
+    ClanBadges, ClanTag, Client, GetCurrentWar, GetWarLog, LoadError, Page, PlayerGamesStats,
+    PlayerTag, Time, WarLog, WarLogEntry,
+};
 
 pub struct WarClient<'c> {
     client: &'c Client,
@@ -13,56 +16,19 @@ impl<'c> WarClient<'c> {
     }
 
     pub async fn logs(&self, clan: &ClanTag) -> Result<WarLog, LoadError> {
-        let resp = self
-            .client
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clans/%23{}/warlog?limit=10",
-                clan.0.as_str().strip_prefix("#").unwrap_or(clan.0.as_str())
-            ))
-            .bearer_auth(&self.client.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if resp.status() != StatusCode::OK {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
+        self.client.request(&GetWarLog(clan, Page::first(10))).await
+    }
 
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+    /// All the Entries of the War-Log, starting with the most recent War
+    pub fn all_logs(
+        &self,
+        clan: &'c ClanTag,
+    ) -> impl Stream<Item = Result<WarLogEntry, LoadError>> + 'c {
+        self.client.paginate(GetWarLog(clan, Page::default()))
     }
 
     pub async fn current(&self, clan: &ClanTag) -> Result<CurrentWar, LoadError> {
-        let resp = self
-            .client
-            .client
-            .get(format!(
-                "https://api.clashofclans.com/v1/clans/%23{}/currentwar",
-                clan.0.as_str().strip_prefix("#").unwrap_or(clan.0.as_str())
-            ))
-            .bearer_auth(&self.client.api_key)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(r) => r,
-            Err(e) => {
-                tracing::error!("{:?}", e);
-                return Err(LoadError::ReqwestError(e));
-            }
-        };
-
-        if resp.status() != StatusCode::OK {
-            return Err(LoadError::NotOkResponse(resp.status()));
-        }
-
-        resp.json().await.map_err(|e| LoadError::Deserialize(e))
+        self.client.request(&GetCurrentWar(clan)).await
     }
 }
 
@@ -74,11 +40,11 @@ pub struct CurrentWar {
     #[serde(rename = "teamSize")]
     team_size: Option<usize>,
     #[serde(rename = "attacksPerMember")]
-    attacks_per_member: Option<usize>,
+    pub attacks_per_member: Option<usize>,
     #[serde(rename = "startTime")]
     pub start_time: Option<Time>,
     #[serde(rename = "endTime")]
-    end_time: Option<String>,
+    pub end_time: Option<String>,
     #[serde(rename = "preparationStartTime")]
     preparation_start_time: Option<String>,
 }
@@ -127,7 +93,7 @@ pub struct WarClan {
 #[derive(Debug, Deserialize)]
 pub struct WarClanMember {
     mapPosition: usize,
-    name: String,
+    pub name: String,
     opponentAttacks: usize,
     pub tag: PlayerTag,
     townhallLevel: usize,
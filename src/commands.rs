@@ -0,0 +1,1116 @@
+//! The Discord Application-Commands offered by the Bot
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use gold_pass_bot::{
+    ClanStorage, ClanTag, ExcelStats, PlayerDetails, PlayerLinks, PlayerSummary, PlayerTag,
+    RosterChange, RosterEvent, Score, ScoringConfig, Season, Storage, WarAttack,
+};
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommandOption, CreateApplicationCommands};
+use serenity::model::application::command::{Command, CommandOptionType};
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::application::interaction::{Interaction, InteractionResponseType};
+use serenity::model::gateway::Ready;
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::prelude::AttachmentType;
+use serenity::model::Permissions;
+use serenity::prelude::*;
+
+pub struct ClanStates;
+impl TypeMapKey for ClanStates {
+    type Value = Arc<ArcSwap<(Storage, u64)>>;
+}
+
+pub struct TrackedClans;
+impl TypeMapKey for TrackedClans {
+    type Value = Arc<Vec<ClanTag>>;
+}
+
+pub struct Scoring;
+impl TypeMapKey for Scoring {
+    type Value = Arc<ScoringConfig>;
+}
+
+/// The Links between Discord-Users and Players, these are owned by the Commands and copied into
+/// the Storage by the Collector
+pub struct Links;
+impl TypeMapKey for Links {
+    type Value = Arc<ArcSwap<PlayerLinks>>;
+}
+
+pub struct ApiClient;
+impl TypeMapKey for ApiClient {
+    type Value = Arc<gold_pass_bot::Client>;
+}
+
+pub struct Handler {
+    /// The Guild to register the Commands in, if not set the Commands are registered globally
+    pub guild_id: Option<GuildId>,
+    /// Whether linking a Player requires the in-game API-Token
+    pub verify_links: bool,
+}
+
+/// The Response to an Application-Command.
+///
+/// The first Message is sent as the Response to the Interaction itself and all the remaining
+/// Messages are sent as Followups.
+struct Response {
+    messages: Vec<String>,
+    files: Vec<AttachmentType<'static>>,
+    ephemeral: bool,
+    /// The Interaction was already acknowledged using [`defer`], so the first Message replaces
+    /// the loading Message. Deferred Responses can not contain Files.
+    deferred: bool,
+}
+
+impl Response {
+    fn public<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            messages: vec![content.into()],
+            files: Vec::new(),
+            ephemeral: false,
+            deferred: false,
+        }
+    }
+
+    /// A Response that is only visible to the User that invoked the Command
+    fn ephemeral<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            ephemeral: true,
+            ..Self::public(content)
+        }
+    }
+
+    fn deferred(self) -> Self {
+        Self {
+            deferred: true,
+            ..self
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        tracing::info!("Connected as {}", ready.user.name);
+
+        let tracked = {
+            let guard = ctx.data.read().await;
+            guard.get::<TrackedClans>().unwrap().clone()
+        };
+
+        let result = match self.guild_id {
+            Some(guild) => guild
+                .set_application_commands(&ctx.http, |c| register_commands(c, &tracked))
+                .await
+                .map(|_| ()),
+            None => Command::set_global_application_commands(&ctx.http, |c| {
+                register_commands(c, &tracked)
+            })
+            .await
+            .map(|_| ()),
+        };
+
+        if let Err(e) = result {
+            tracing::error!("Registering Application Commands: {:?}", e);
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let command = match interaction {
+            Interaction::ApplicationCommand(c) => c,
+            _ => return,
+        };
+
+        tracing::debug!("Received Command {:?}", command.data.name);
+
+        let response = match command.data.name.as_str() {
+            "stats" => stats(&ctx, &command).await,
+            "export" => export(&ctx, &command).await,
+            "roster" => roster(&ctx, &command).await,
+            "winner" => winner(&ctx, &command).await,
+            "player" => player(&ctx, &command).await,
+            "me" => me(&ctx, &command).await,
+            "link" => link(&ctx, &command, self.verify_links).await,
+            "unlink" => unlink(&ctx, &command).await,
+            "admin-link" => admin_link(&ctx, &command).await,
+            "admin-unlink" => admin_unlink(&ctx, &command).await,
+            other => Response::ephemeral(format!("Unknown Command {:?}", other)),
+        };
+
+        send_response(&ctx, &command, response).await;
+    }
+}
+
+fn register_commands<'a>(
+    commands: &'a mut CreateApplicationCommands,
+    tracked: &[ClanTag],
+) -> &'a mut CreateApplicationCommands {
+    commands
+        .create_application_command(|c| {
+            c.name("stats")
+                .description("Shows the Stats of all Players for a Season")
+                .add_option(clan_option(tracked))
+                .add_option(season_option())
+                .add_option(former_option())
+        })
+        .create_application_command(|c| {
+            c.name("export")
+                .description("Exports the Stats as Spreadsheets")
+                .add_option(clan_option(tracked))
+                .add_option(season_option())
+                .add_option(former_option())
+        })
+        .create_application_command(|c| {
+            c.name("roster")
+                .description("Shows who joined or left the Clan and changed Roles in a Season")
+                .add_option(clan_option(tracked))
+                .add_option(season_option())
+        })
+        .create_application_command(|c| {
+            c.name("winner")
+                .description("Announces the Gold-Pass winners of the previous Season")
+                .create_option(|o| {
+                    o.name("count")
+                        .description("The Number of Players to announce")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(1)
+                        .max_int_value(25)
+                        .required(false)
+                })
+                .add_option(clan_option(tracked))
+        })
+        .create_application_command(|c| {
+            c.name("player")
+                .description("Shows the detailed Stats of a single Player for a Season")
+                .create_option(|o| {
+                    o.name("player")
+                        .description("The Tag or Name of the Player")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .add_option(clan_option(tracked))
+                .add_option(season_option())
+        })
+        .create_application_command(|c| {
+            c.name("me")
+                .description("Shows the detailed Stats of your linked Players for a Season")
+                .add_option(clan_option(tracked))
+                .add_option(season_option())
+        })
+        .create_application_command(|c| {
+            c.name("link")
+                .description("Links one of your Clash of Clans Accounts to your Discord User")
+                .add_option(player_tag_option())
+                .create_option(|o| {
+                    o.name("token")
+                        .description("The API-Token from the in-game Settings of the Account")
+                        .kind(CommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_application_command(|c| {
+            c.name("unlink")
+                .description("Removes the Link to one of your Clash of Clans Accounts")
+                .add_option(player_tag_option())
+        })
+        .create_application_command(|c| {
+            c.name("admin-link")
+                .description("Links a Clash of Clans Account to the given Discord User")
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .create_option(|o| {
+                    o.name("user")
+                        .description("The Discord User owning the Account")
+                        .kind(CommandOptionType::User)
+                        .required(true)
+                })
+                .add_option(player_tag_option())
+        })
+        .create_application_command(|c| {
+            c.name("admin-unlink")
+                .description("Removes the Link of a Clash of Clans Account")
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(player_tag_option())
+        })
+}
+
+fn player_tag_option() -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option
+        .name("player")
+        .description("The Tag of the Player")
+        .kind(CommandOptionType::String)
+        .required(true);
+    option
+}
+
+/// The Option for selecting one of the tracked Clans, offers the tracked Clans as choices if
+/// Discord allows for that many
+fn clan_option(tracked: &[ClanTag]) -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option
+        .name("clan")
+        .description("The Tag of the Clan, can be omitted if only one Clan is tracked")
+        .kind(CommandOptionType::String)
+        .required(false);
+
+    if tracked.len() <= 25 {
+        for tag in tracked {
+            option.add_string_choice(&tag.0, &tag.0);
+        }
+    }
+
+    option
+}
+
+fn season_option() -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option
+        .name("season")
+        .description("The Season in the format YYYY-MM, defaults to the current Season")
+        .kind(CommandOptionType::String)
+        .min_length(7)
+        .max_length(7)
+        .required(false);
+    option
+}
+
+fn former_option() -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option
+        .name("former")
+        .description("Also include the Players that left the Clan during the Season")
+        .kind(CommandOptionType::Boolean)
+        .required(false);
+    option
+}
+
+fn option<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> Option<&'a CommandDataOptionValue> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.resolved.as_ref())
+}
+
+fn string_option<'a>(command: &'a ApplicationCommandInteraction, name: &str) -> Option<&'a str> {
+    match option(command, name) {
+        Some(CommandDataOptionValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+fn integer_option(command: &ApplicationCommandInteraction, name: &str) -> Option<i64> {
+    match option(command, name) {
+        Some(CommandDataOptionValue::Integer(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+fn bool_option(command: &ApplicationCommandInteraction, name: &str) -> Option<bool> {
+    match option(command, name) {
+        Some(CommandDataOptionValue::Boolean(b)) => Some(*b),
+        _ => None,
+    }
+}
+
+fn user_option(command: &ApplicationCommandInteraction, name: &str) -> Option<UserId> {
+    match option(command, name) {
+        Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+        _ => None,
+    }
+}
+
+async fn send_response(ctx: &Context, command: &ApplicationCommandInteraction, response: Response) {
+    let Response {
+        messages,
+        files,
+        ephemeral,
+        deferred,
+    } = response;
+    let mut messages = messages.into_iter();
+    let first = messages.next().unwrap_or_default();
+
+    let result = if deferred {
+        command
+            .edit_original_interaction_response(&ctx.http, |r| r.content(first))
+            .await
+            .map(|_| ())
+    } else {
+        command
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content(first).ephemeral(ephemeral).files(files)
+                    })
+            })
+            .await
+    };
+    if let Err(e) = result {
+        tracing::error!("Sending Response: {:?}", e);
+        return;
+    }
+
+    for (index, content) in messages.enumerate() {
+        tracing::trace!("Sending Followup: {}", index);
+
+        if let Err(e) = command
+            .create_followup_message(&ctx.http, |m| m.content(content).ephemeral(ephemeral))
+            .await
+        {
+            tracing::error!("Sending Followup: {:?}", e);
+        }
+    }
+}
+
+/// Acknowledges the Command with a loading Message, for Commands that might take longer to
+/// respond than Discord waits for. The Response then has to be marked as [`Response::deferred`].
+async fn defer(ctx: &Context, command: &ApplicationCommandInteraction, ephemeral: bool) {
+    if let Err(e) = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|d| d.ephemeral(ephemeral))
+        })
+        .await
+    {
+        tracing::error!("Deferring Response: {:?}", e);
+    }
+}
+
+/// Determines the Clan a command should apply to.
+///
+/// If the command specifies a Clan-Tag it has to be one of the tracked Clans, otherwise the only
+/// tracked Clan is used. In case no Clan could be determined, the Response for the user is
+/// returned as the Error.
+async fn resolve_clan(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> Result<ClanTag, Response> {
+    let tracked = {
+        let guard = ctx.data.read().await;
+        guard.get::<TrackedClans>().unwrap().clone()
+    };
+
+    let response = match string_option(command, "clan") {
+        Some(raw) => match raw.parse::<ClanTag>() {
+            Ok(tag) if tracked.contains(&tag) => return Ok(tag),
+            Ok(tag) => format!("Clan {} is not tracked", tag.0),
+            Err(_) => format!("{:?} is not a valid Clan-Tag", raw),
+        },
+        None if tracked.len() == 1 => return Ok(tracked[0].clone()),
+        None => format!(
+            "Multiple Clans are tracked, please specify one of: {}",
+            tracked
+                .iter()
+                .map(|t| t.0.as_str())
+                .intersperse(", ")
+                .collect::<String>()
+        ),
+    };
+
+    Err(Response::ephemeral(response))
+}
+
+/// Parses the optional Season of the command, the Response for the user is returned as the
+/// Error if the Season is malformed
+fn resolve_season(command: &ApplicationCommandInteraction) -> Result<Option<Season>, Response> {
+    match string_option(command, "season") {
+        Some(raw) => raw.parse().map(Some).map_err(|e| {
+            Response::ephemeral(format!(
+                "{:?} is not a valid Season ({}), please use the format YYYY-MM",
+                raw, e
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+fn missing_stats(clan_tag: &ClanTag, season: &Season) -> Response {
+    Response::ephemeral(format!(
+        "No Stats available for {} {:02}-{}",
+        clan_tag.0, season.month, season.year
+    ))
+}
+
+fn generate_batches(
+    player_count: usize,
+    header_padding_width: usize,
+    padding_width: usize,
+    summaries: BTreeMap<String, (PlayerSummary, Score)>,
+    timestamp: u64,
+) -> Vec<String> {
+    let mut summary_iter = summaries
+        .iter()
+        .map(|(name, (sum, score))| {
+            format!(
+                "{:width$}|  {:2} |   {:2} | {:5} |    {:2} | {:4} | {:5.0}",
+                name,
+                sum.cwl_stars,
+                sum.war_stars,
+                sum.raid_loot,
+                sum.games_score,
+                sum.donations,
+                score.total(),
+                width = padding_width
+            )
+        })
+        .peekable();
+    let line_width = format!(
+        "{:width$}|  {:2} |   {:2} | {:5} |    {:2} | {:4} | {:5.0}",
+        "",
+        0,
+        0,
+        0,
+        0,
+        0,
+        0.0,
+        width = padding_width
+    )
+    .len();
+
+    let players_per_line = 1800 / line_width;
+    (0..(player_count / players_per_line + 1))
+        .map(|batch| {
+            let summary: String = core::iter::once(format!(
+                "```Player Tag {:width$}| CWL | Wars | Raids | Games | Dons | Score",
+                ' ',
+                width = header_padding_width
+            ))
+            .chain(core::iter::once(
+                core::iter::repeat('-')
+                    .take(55 + padding_width.saturating_sub(11))
+                    .collect(),
+            ))
+            .chain(summary_iter.by_ref().take(players_per_line))
+            .chain(core::iter::once("```".to_string()))
+            .intersperse("\n".to_string())
+            .collect();
+
+            format!(
+                "{}\nTimestamp: {}\n{}/{}",
+                summary,
+                timestamp,
+                batch + 1,
+                player_count / players_per_line + 1
+            )
+        })
+        .collect()
+}
+
+async fn stats(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let season = match resolve_season(command) {
+        Ok(s) => s.unwrap_or_else(Season::current),
+        Err(resp) => return resp,
+    };
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+    let scoring = guard.get::<Scoring>().unwrap();
+
+    let stats_guard = storage.load();
+    let (stats, timestamp) = stats_guard.as_ref();
+
+    tracing::trace!("Displaying stats for season: {:?}", season);
+
+    let clan_stats = match stats.get(&clan_tag, &season) {
+        Some(s) => s,
+        None => return missing_stats(&clan_tag, &season),
+    };
+
+    let include_former = bool_option(command, "former").unwrap_or(false);
+    let player_summaries: BTreeMap<_, _> = clan_stats
+        .players_summary(include_former)
+        .map(|(tag, v)| {
+            let score = scoring.score(&v);
+            let name = clan_stats.player_names.get(&tag).unwrap();
+            let name = if clan_stats.is_former_member(&tag) {
+                format!("{} (left)", name)
+            } else {
+                name.clone()
+            };
+            (name, (v, score))
+        })
+        .collect();
+
+    let player_count = player_summaries.len();
+    tracing::trace!("Sending Summary for {} Players", player_count);
+
+    let max_name_length = player_summaries.keys().map(|n| n.len()).max().unwrap_or(0);
+
+    let padding_width = 11.max(max_name_length);
+    let header_padding_width = padding_width.saturating_sub(11);
+
+    let batches = generate_batches(
+        player_count,
+        header_padding_width,
+        padding_width,
+        player_summaries,
+        *timestamp,
+    );
+
+    let mut response = Response::public(format!(
+        "Stats for {} {:02}-{}",
+        clan_tag.0, season.month, season.year
+    ));
+    response.messages.extend(batches);
+    response
+}
+
+async fn export(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let seasons = match resolve_season(command) {
+        Ok(Some(season)) => vec![season],
+        Ok(None) => {
+            let current_season = Season::current();
+            let last_season = current_season.previous();
+            vec![current_season, last_season]
+        }
+        Err(resp) => return resp,
+    };
+    let include_former = bool_option(command, "former").unwrap_or(false);
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+    let scoring = guard.get::<Scoring>().unwrap();
+
+    let stats_guard = storage.load();
+    let (stats, _timestamp) = stats_guard.as_ref(); // TODO
+
+    tracing::trace!("Displaying stats for seasons: {:?}", seasons);
+
+    if let Some(season) = seasons.iter().find(|s| stats.get(&clan_tag, s).is_none()) {
+        return missing_stats(&clan_tag, season);
+    }
+
+    let files = seasons.into_iter().map(|season| {
+        let clan_stats = stats.get(&clan_tag, &season).unwrap();
+
+        let mut excel_book = ExcelStats::new(scoring.as_ref().clone())
+            .with_former_members(include_former)
+            .populate_workbook(clan_stats);
+        let content = excel_book.save_to_buffer().unwrap();
+
+        AttachmentType::Bytes {
+            data: Cow::Owned(content),
+            filename: format!(
+                "Tracker - {} - {}-{}.xlsx",
+                clan_tag.0, season.month, season.year
+            ),
+        }
+    });
+
+    let mut response = Response::public("Populated Spreadsheet");
+    response.files.extend(files);
+    response
+}
+
+/// The maximum Number of Roster Changes shown at once, keeps the Message below Discord's limit
+const ROSTER_CHANGES: usize = 20;
+
+fn format_roster_event(event: &RosterEvent, clan_stats: &ClanStorage) -> String {
+    let name = clan_stats
+        .player_names
+        .get(&event.player)
+        .map(|n| n.as_str())
+        .unwrap_or("");
+    let change = match &event.change {
+        RosterChange::Joined { role } => format!("joined as {}", role),
+        RosterChange::Left { role } => format!("left as {}", role),
+        RosterChange::RoleChanged { from, to } => format!("{} -> {}", from, to),
+    };
+
+    format!(
+        "{} {} ({}) {}",
+        event.time.format("%Y-%m-%d %H:%M"),
+        name,
+        event.player.0,
+        change
+    )
+}
+
+async fn roster(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let season = match resolve_season(command) {
+        Ok(s) => s.unwrap_or_else(Season::current),
+        Err(resp) => return resp,
+    };
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+
+    let stats_guard = storage.load();
+    let (stats, _) = stats_guard.as_ref();
+
+    let clan_stats = match stats.get(&clan_tag, &season) {
+        Some(s) => s,
+        None => return missing_stats(&clan_tag, &season),
+    };
+
+    let events = &clan_stats.roster.events;
+    let mut lines: Vec<String> = events
+        .iter()
+        .rev()
+        .take(ROSTER_CHANGES)
+        .map(|event| format_roster_event(event, clan_stats))
+        .collect();
+    if events.is_empty() {
+        lines.push("No Changes".to_string());
+    }
+    if events.len() > ROSTER_CHANGES {
+        lines.push(format!(
+            "... and {} older Changes",
+            events.len() - ROSTER_CHANGES
+        ));
+    }
+
+    Response::public(format!(
+        "Roster of {} {:02}-{} ({} Members)\n```{}```",
+        clan_tag.0,
+        season.month,
+        season.year,
+        clan_stats.roster.members.len(),
+        lines
+            .into_iter()
+            .intersperse("\n".to_string())
+            .collect::<String>()
+    ))
+}
+
+async fn winner(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let count = integer_option(command, "count").unwrap_or(3).clamp(1, 25) as usize;
+
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+    let scoring = guard.get::<Scoring>().unwrap();
+
+    let stats_guard = storage.load();
+    let (stats, _) = stats_guard.as_ref();
+
+    let season = Season::current().previous();
+    tracing::trace!("Determining winners for season: {:?}", season);
+
+    let clan_stats = match stats.get(&clan_tag, &season) {
+        Some(s) => s,
+        None => return missing_stats(&clan_tag, &season),
+    };
+
+    let leaderboard = clan_stats.leaderboard(scoring);
+    if leaderboard.is_empty() {
+        return Response::ephemeral(format!(
+            "No Players found for {} {:02}-{}",
+            clan_tag.0, season.month, season.year
+        ));
+    }
+
+    let winners: String = leaderboard
+        .iter()
+        .take(count)
+        .map(|player| {
+            format!(
+                "{}. {} ({}) - {:.0} Points",
+                player.rank,
+                clan_stats
+                    .player_names
+                    .get(&player.tag)
+                    .map(|n| n.as_str())
+                    .unwrap_or(""),
+                player.tag.0,
+                player.score.total()
+            )
+        })
+        .intersperse("\n".to_string())
+        .collect();
+
+    Response::public(format!(
+        "Gold-Pass Winners for {} {:02}-{}\n{}",
+        clan_tag.0, season.month, season.year, winners
+    ))
+}
+
+fn format_attacks(attacks: &[WarAttack]) -> String {
+    if attacks.is_empty() {
+        return "No Attacks".to_string();
+    }
+
+    attacks
+        .iter()
+        .map(|a| format!("{}* {:3}% {:3}s", a.stars, a.destruction, a.duration))
+        .intersperse(", ".to_string())
+        .collect()
+}
+
+fn format_player_details(details: &PlayerDetails<'_>, score: &Score) -> String {
+    let mut lines = Vec::new();
+
+    lines.push("CWL".to_string());
+    if details.cwl.is_empty() {
+        lines.push("  No CWL Rounds".to_string());
+    }
+    for (round, attacks) in details.cwl.iter().enumerate() {
+        let content = match attacks {
+            Some(attacks) => format_attacks(attacks),
+            None => "Not in Lineup".to_string(),
+        };
+        lines.push(format!("  Round {}: {}", round + 1, content));
+    }
+
+    lines.push("Wars".to_string());
+    if details.wars.is_empty() {
+        lines.push("  No Wars".to_string());
+    }
+    for (start, attacks) in details.wars.iter() {
+        lines.push(format!("  {}: {}", start, format_attacks(attacks)));
+    }
+    if details.summary_only_wars > 0 {
+        lines.push(format!(
+            "  {} Wars only known from the War-Log, without Attacks",
+            details.summary_only_wars
+        ));
+    }
+
+    lines.push("Raids".to_string());
+    if details.raids.is_empty() {
+        lines.push("  No Raids".to_string());
+    }
+    for (start, looted) in details.raids.iter() {
+        lines.push(format!("  {}: {:6} Loot", start, looted));
+    }
+
+    lines.push(format!(
+        "Clan Games: {}",
+        details
+            .games
+            .map(|g| format!("{} Points", g))
+            .unwrap_or_else(|| "No Data".to_string())
+    ));
+    lines.push(format!(
+        "Donations: {} (Received {})",
+        details.summary.donations, details.summary.donations_received
+    ));
+    lines.push(format!(
+        "Score: {:.0} (CWL {:.0}, Wars {:.0}, Raids {:.0}, Games {:.0}, Donations {:.0})",
+        score.total(),
+        score.cwl,
+        score.war,
+        score.raid,
+        score.games,
+        score.donations
+    ));
+
+    lines.into_iter().intersperse("\n".to_string()).collect()
+}
+
+async fn player(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let season = match resolve_season(command) {
+        Ok(s) => s.unwrap_or_else(Season::current),
+        Err(resp) => return resp,
+    };
+    let query = string_option(command, "player").unwrap_or("");
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+    let scoring = guard.get::<Scoring>().unwrap();
+
+    let stats_guard = storage.load();
+    let (stats, _) = stats_guard.as_ref();
+
+    let clan_stats = match stats.get(&clan_tag, &season) {
+        Some(s) => s,
+        None => return missing_stats(&clan_tag, &season),
+    };
+
+    let player_tag = match clan_stats.find_player(query) {
+        Some(t) => t,
+        None => {
+            return Response::ephemeral(format!(
+                "Could not find a unique Player {:?} in {} {:02}-{}",
+                query, clan_tag.0, season.month, season.year
+            ));
+        }
+    };
+
+    let details = clan_stats.player_details(&player_tag);
+    let score = scoring.score(&details.summary);
+
+    Response::ephemeral(format!(
+        "Stats for {} ({}) in {} {:02}-{}\n```{}```",
+        details.name.unwrap_or(""),
+        details.tag.0,
+        clan_tag.0,
+        season.month,
+        season.year,
+        format_player_details(&details, &score)
+    ))
+}
+
+async fn me(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let clan_tag = match resolve_clan(ctx, command).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let season = match resolve_season(command) {
+        Ok(s) => s.unwrap_or_else(Season::current),
+        Err(resp) => return resp,
+    };
+
+    let guard = ctx.data.read().await;
+    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
+    let scoring = guard.get::<Scoring>().unwrap();
+    let links = guard.get::<Links>().unwrap().load();
+
+    let players = links.players(command.user.id.0);
+    if players.is_empty() {
+        return Response::ephemeral("You have no linked Players, use /link to link your Account");
+    }
+
+    let stats_guard = storage.load();
+    let (stats, _) = stats_guard.as_ref();
+
+    let clan_stats = match stats.get(&clan_tag, &season) {
+        Some(s) => s,
+        None => return missing_stats(&clan_tag, &season),
+    };
+
+    let messages: Vec<_> = players
+        .into_iter()
+        .filter(|tag| clan_stats.player_names.contains_key(tag))
+        .map(|tag| {
+            let details = clan_stats.player_details(tag);
+            let score = scoring.score(&details.summary);
+
+            format!(
+                "Stats for {} ({}) in {} {:02}-{}\n```{}```",
+                details.name.unwrap_or(""),
+                details.tag.0,
+                clan_tag.0,
+                season.month,
+                season.year,
+                format_player_details(&details, &score)
+            )
+        })
+        .collect();
+
+    if messages.is_empty() {
+        return Response::ephemeral(format!(
+            "None of your linked Players are part of {} {:02}-{}",
+            clan_tag.0, season.month, season.year
+        ));
+    }
+
+    Response {
+        messages,
+        files: Vec::new(),
+        ephemeral: true,
+        deferred: false,
+    }
+}
+
+/// Parses the required Player-Tag of the command, the Response for the user is returned as the
+/// Error if the Tag is malformed
+fn resolve_player_tag(command: &ApplicationCommandInteraction) -> Result<PlayerTag, Response> {
+    let raw = string_option(command, "player").unwrap_or("");
+    raw.parse()
+        .map_err(|_| Response::ephemeral(format!("{:?} is not a valid Player-Tag", raw)))
+}
+
+fn is_admin(command: &ApplicationCommandInteraction) -> bool {
+    command
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .map(|p| p.manage_guild())
+        .unwrap_or(false)
+}
+
+async fn link(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    verify_links: bool,
+) -> Response {
+    let player_tag = match resolve_player_tag(command) {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let user = command.user.id.0;
+
+    let (links, api_client) = {
+        let guard = ctx.data.read().await;
+        (
+            guard.get::<Links>().unwrap().clone(),
+            guard.get::<ApiClient>().unwrap().clone(),
+        )
+    };
+
+    match links.load().user(&player_tag) {
+        Some(owner) if owner == user => {
+            return Response::ephemeral(format!("{} is already linked to you", player_tag.0));
+        }
+        Some(_) => {
+            return Response::ephemeral(format!(
+                "{} is already linked to another User, please contact an Admin",
+                player_tag.0
+            ));
+        }
+        None => {}
+    };
+
+    let deferred = match string_option(command, "token") {
+        Some(token) => {
+            // The Request to the API might be delayed by the Rate-Limit and Retries
+            defer(ctx, command, true).await;
+
+            match api_client.verify_player_token(&player_tag, token).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Response::ephemeral(format!("Invalid API-Token for {}", player_tag.0))
+                        .deferred();
+                }
+                Err(e) => {
+                    tracing::error!("Verifying Token: {:?}", e);
+                    return Response::ephemeral(
+                        "Could not verify the API-Token, please try again later",
+                    )
+                    .deferred();
+                }
+            }
+            true
+        }
+        None if verify_links => {
+            return Response::ephemeral(
+                "Please provide the API-Token found in the in-game Settings of your Account",
+            );
+        }
+        None => false,
+    };
+
+    links.rcu(|current| {
+        let mut updated = current.as_ref().clone();
+        updated.link(user, player_tag.clone());
+        updated
+    });
+
+    let response = Response::ephemeral(format!("Linked {} to your Account", player_tag.0));
+    if deferred {
+        response.deferred()
+    } else {
+        response
+    }
+}
+
+async fn unlink(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    let player_tag = match resolve_player_tag(command) {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let user = command.user.id.0;
+
+    let guard = ctx.data.read().await;
+    let links = guard.get::<Links>().unwrap();
+
+    if links.load().user(&player_tag) != Some(user) {
+        return Response::ephemeral(format!("{} is not linked to you", player_tag.0));
+    }
+
+    links.rcu(|current| {
+        let mut updated = current.as_ref().clone();
+        updated.unlink(&player_tag);
+        updated
+    });
+
+    Response::ephemeral(format!("Unlinked {} from your Account", player_tag.0))
+}
+
+async fn admin_link(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    if !is_admin(command) {
+        return Response::ephemeral("This Command is only available to Admins");
+    }
+
+    let player_tag = match resolve_player_tag(command) {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let user = match user_option(command, "user") {
+        Some(u) => u,
+        None => return Response::ephemeral("Missing the User to link"),
+    };
+
+    let guard = ctx.data.read().await;
+    let links = guard.get::<Links>().unwrap();
+
+    let previous = links.load().user(&player_tag);
+    links.rcu(|current| {
+        let mut updated = current.as_ref().clone();
+        updated.link(user.0, player_tag.clone());
+        updated
+    });
+
+    match previous {
+        Some(prev) if prev != user.0 => Response::ephemeral(format!(
+            "Linked {} to <@{}>, previously linked to <@{}>",
+            player_tag.0, user.0, prev
+        )),
+        _ => Response::ephemeral(format!("Linked {} to <@{}>", player_tag.0, user.0)),
+    }
+}
+
+async fn admin_unlink(ctx: &Context, command: &ApplicationCommandInteraction) -> Response {
+    if !is_admin(command) {
+        return Response::ephemeral("This Command is only available to Admins");
+    }
+
+    let player_tag = match resolve_player_tag(command) {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+
+    let guard = ctx.data.read().await;
+    let links = guard.get::<Links>().unwrap();
+
+    let previous = links.load().user(&player_tag);
+    match previous {
+        Some(user) => {
+            links.rcu(|current| {
+                let mut updated = current.as_ref().clone();
+                updated.unlink(&player_tag);
+                updated
+            });
+            Response::ephemeral(format!("Unlinked {} from <@{}>", player_tag.0, user))
+        }
+        None => Response::ephemeral(format!("{} is not linked", player_tag.0)),
+    }
+}
@@ -0,0 +1,652 @@
+use std::{net::SocketAddr, path::Path, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    ActiveWindow, ClanTag, Collector, RateLimiter, RetryPolicy, Schedule, ScoringConfig,
+    WritePolicy, DEFAULT_BASE_URL,
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    /// A value is outside of its allowed range
+    Invalid(&'static str),
+}
+
+/// The Configuration for the `bot` subcommand
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The Clans that should be tracked
+    pub clans: Vec<ClanTag>,
+    pub api: ApiConfig,
+    pub storage: StorageConfig,
+    pub collector: CollectorConfig,
+    pub clan_games: ClanGamesConfig,
+    pub http: HttpConfig,
+    pub scoring: ScoringConfig,
+    pub discord: DiscordConfig,
+    pub reminders: ReminderConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// The Path to the file containing the Clash of Clans API-Key
+    pub key_path: String,
+    /// The URL of the API, allows using a Proxy instead of the official API
+    pub base_url: String,
+    /// The Number of Requests per second sent to the API
+    pub requests_per_sec: f64,
+    /// The Number of Requests that can be sent at once before being limited
+    pub burst: u32,
+    /// The Number of seconds after which a Request times out
+    pub timeout_secs: u64,
+    /// The Number of times a Request is retried after Timeouts, Rate Limits or Server Errors
+    pub max_retries: u32,
+    /// The Delay before the first Retry in milliseconds, doubled for every following Retry
+    pub retry_base_ms: u64,
+    /// The maximum Delay between two Retries in seconds
+    pub retry_max_secs: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// The Storage Backends to use, multiple Backends are replicated in the given order
+    pub chain: Vec<StorageKind>,
+    /// How many of the replicated Backends need to be written successfully
+    pub write_policy: WritePolicy,
+    pub file: FileStorageConfig,
+    pub s3: Option<S3StorageConfig>,
+    pub sqlite: SqliteStorageConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    File,
+    S3,
+    Sqlite,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileStorageConfig {
+    pub path: String,
+    /// The Number of previous Versions of the File to keep as Backups
+    pub backups: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SqliteStorageConfig {
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3StorageConfig {
+    pub bucket: String,
+    pub endpoint: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// The Prefix for all the Objects, allows multiple Instances to share a Bucket
+    pub prefix: String,
+    /// The Key of the Object containing the Data
+    pub key: String,
+    /// Stores periodic Snapshots below `<prefix>/snapshots/` if set
+    pub snapshots: Option<S3SnapshotConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3SnapshotConfig {
+    /// The Number of seconds between two Snapshots
+    pub interval_secs: u64,
+    /// The Number of Snapshots to keep
+    pub keep: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectorConfig {
+    /// The Number of seconds between publishing and saving the collected Stats
+    pub interval_secs: u64,
+    pub names: ScheduleConfig,
+    pub war: ScheduleConfig,
+    pub cwl: ScheduleConfig,
+    pub clan_games: ScheduleConfig,
+    pub raids: ScheduleConfig,
+}
+
+/// Overrides the default Schedule of a Collector, see [`Collector::default_schedule`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// The Number of seconds between two Runs of the Collector
+    pub interval_secs: Option<u64>,
+    /// The maximum Number of seconds between two Runs while the Collector keeps failing
+    pub max_backoff_secs: Option<u64>,
+    pub window: Option<ActiveWindow>,
+}
+
+/// The Dates of the Clan Games Event in every Season, the Event starts and ends at `hour` UTC of
+/// the given Days of the Month
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClanGamesConfig {
+    pub start_day: u32,
+    pub end_day: u32,
+    pub hour: u32,
+    /// The maximum Number of Points a single Player can earn in the Event
+    pub max_points: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// The Address the metrics endpoint is served on
+    pub bind: SocketAddr,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub token: Option<String>,
+    /// The Guild in which the Commands are registered, registers them globally if not set
+    pub guild_id: Option<u64>,
+    /// Require Users to verify the ownership of a Player using the in-game API-Token when
+    /// linking their Account
+    pub verify_links: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReminderConfig {
+    /// The Channel in which the Reminders for missed Attacks are posted, disabled if not set
+    pub channel_id: Option<u64>,
+    /// The Number of seconds before the end of a War at which the Reminder is posted
+    pub before_end_secs: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            key_path: "api.key".to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            requests_per_sec: 10.0,
+            burst: 20,
+            timeout_secs: 30,
+            max_retries: 3,
+            retry_base_ms: 500,
+            retry_max_secs: 60,
+        }
+    }
+}
+
+impl Default for FileStorageConfig {
+    fn default() -> Self {
+        Self {
+            path: "data.json".to_string(),
+            backups: 3,
+        }
+    }
+}
+
+impl Default for SqliteStorageConfig {
+    fn default() -> Self {
+        Self {
+            path: "data.sqlite".to_string(),
+        }
+    }
+}
+
+impl Default for S3StorageConfig {
+    fn default() -> Self {
+        Self {
+            bucket: String::new(),
+            endpoint: String::new(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            prefix: String::new(),
+            key: "storage.json".to_string(),
+            snapshots: None,
+        }
+    }
+}
+
+impl Default for S3SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 24 * 60 * 60,
+            keep: 30,
+        }
+    }
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 90,
+            names: ScheduleConfig::default(),
+            war: ScheduleConfig::default(),
+            cwl: ScheduleConfig::default(),
+            clan_games: ScheduleConfig::default(),
+            raids: ScheduleConfig::default(),
+        }
+    }
+}
+
+impl Default for ClanGamesConfig {
+    fn default() -> Self {
+        Self {
+            start_day: 22,
+            end_day: 28,
+            hour: 8,
+            max_points: 4000,
+        }
+    }
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            channel_id: None,
+            before_end_secs: 3600,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 8080)),
+        }
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Self::File),
+            "s3" => Ok(Self::S3),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ApiConfig {
+    pub fn rate_limiter(&self) -> RateLimiter {
+        RateLimiter::new(self.requests_per_sec, self.burst)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_base_ms),
+            max_delay: Duration::from_secs(self.retry_max_secs),
+        }
+    }
+}
+
+impl CollectorConfig {
+    /// The Schedule of the Collector, combining the configured and the default values
+    pub fn schedule(&self, collector: Collector) -> Schedule {
+        let config = match collector {
+            Collector::Names => &self.names,
+            Collector::War => &self.war,
+            Collector::Cwl => &self.cwl,
+            Collector::ClanGames => &self.clan_games,
+            Collector::Raids => &self.raids,
+        };
+        let default = collector.default_schedule();
+
+        Schedule {
+            interval: config
+                .interval_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.interval),
+            max_backoff: config
+                .max_backoff_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.max_backoff),
+            window: config.window.unwrap_or(default.window),
+        }
+    }
+}
+
+impl ClanGamesConfig {
+    /// The Window in which the Clan Games Collector is run by default, starts a Day before the
+    /// Event to snapshot the Scores right before it starts
+    pub fn window(&self) -> ActiveWindow {
+        ActiveWindow::Days {
+            first: self.start_day.saturating_sub(1).max(1),
+            last: self.end_day,
+        }
+    }
+}
+
+impl Config {
+    /// The Schedule of the Collector, the Clan Games Collector follows the Clan Games Dates unless
+    /// it has its own Window configured
+    pub fn schedule(&self, collector: Collector) -> Schedule {
+        let mut schedule = self.collector.schedule(collector);
+        if collector == Collector::ClanGames && self.collector.clan_games.window.is_none() {
+            schedule.window = self.clan_games.window();
+        }
+        schedule
+    }
+
+    /// Loads the Configuration from the given TOML file
+    pub fn load<P>(path: P) -> Result<Self, ConfigError>
+    where
+        P: AsRef<Path>,
+    {
+        let content = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(content).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values that can be parsed, but can not be used
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.api.requests_per_sec <= 0.0 || self.api.requests_per_sec.is_nan() {
+            return Err(ConfigError::Invalid(
+                "api.requests_per_sec must be positive",
+            ));
+        }
+
+        let games = &self.clan_games;
+        if !(1..=31).contains(&games.start_day) || !(1..=31).contains(&games.end_day) {
+            return Err(ConfigError::Invalid(
+                "clan_games.start_day and clan_games.end_day must be between 1 and 31",
+            ));
+        }
+        if games.start_day > games.end_day {
+            return Err(ConfigError::Invalid(
+                "clan_games.start_day must not be after clan_games.end_day",
+            ));
+        }
+        if games.hour >= 24 {
+            return Err(ConfigError::Invalid("clan_games.hour must be less than 24"));
+        }
+
+        Ok(())
+    }
+
+    /// Applies the overrides from the environment, see [`Config::apply_overrides`]
+    pub fn apply_env(&mut self) {
+        self.apply_overrides(|name| std::env::var(name).ok());
+    }
+
+    /// Overrides the configured values with the values returned by `lookup` for the following
+    /// variables:
+    /// * `API_PATH`, `API_BASE_URL`
+    /// * `STORE_PATH`
+    /// * `S3_BUCKET`, `S3_ENDPOINT`, `S3_ACCESS_KEY`, `S3_SECRET_KEY`
+    /// * `DISCORD_TOKEN`
+    pub fn apply_overrides<F>(&mut self, lookup: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(path) = lookup("API_PATH") {
+            self.api.key_path = path;
+        }
+        if let Some(url) = lookup("API_BASE_URL") {
+            self.api.base_url = url;
+        }
+        if let Some(path) = lookup("STORE_PATH") {
+            self.storage.file.path = path;
+        }
+
+        let s3_overrides =
+            ["S3_BUCKET", "S3_ENDPOINT", "S3_ACCESS_KEY", "S3_SECRET_KEY"].map(&lookup);
+        if s3_overrides.iter().any(|o| o.is_some()) {
+            let s3 = self.storage.s3.get_or_insert_with(Default::default);
+            let [bucket, endpoint, access_key, secret_key] = s3_overrides;
+
+            if let Some(bucket) = bucket {
+                s3.bucket = bucket;
+            }
+            if let Some(endpoint) = endpoint {
+                s3.endpoint = endpoint;
+            }
+            if let Some(access_key) = access_key {
+                s3.access_key = access_key;
+            }
+            if let Some(secret_key) = secret_key {
+                s3.secret_key = secret_key;
+            }
+        }
+
+        if let Some(token) = lookup("DISCORD_TOKEN") {
+            self.discord.token = Some(token);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn parse_full() {
+        let config = Config::parse(
+            r##"
+clans = ["#2L99VLJ9P", "#ABCDEF"]
+
+[api]
+key_path = "/secrets/api.key"
+requests_per_sec = 5.0
+max_retries = 1
+
+[storage]
+chain = ["file", "s3"]
+write_policy = "quorum"
+
+[storage.file]
+path = "/data/data.json"
+backups = 5
+
+[storage.s3]
+bucket = "gold-pass"
+endpoint = "http://localhost:9000"
+access_key = "access"
+secret_key = "secret"
+prefix = "clan-a"
+
+[storage.s3.snapshots]
+keep = 7
+
+[storage.sqlite]
+path = "/data/data.sqlite"
+
+[collector]
+interval_secs = 120
+
+[collector.raids]
+interval_secs = 300
+window = { weekdays = { first = "sat", last = "sun" } }
+
+[clan_games]
+start_day = 20
+end_day = 26
+max_points = 5000
+
+[http]
+bind = "127.0.0.1:9090"
+
+[scoring.cwl]
+target = 24
+
+[discord]
+guild_id = 1234
+verify_links = true
+
+[reminders]
+channel_id = 5678
+before_end_secs = 1800
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ClanTag("#2L99VLJ9P".to_string()),
+                ClanTag("#ABCDEF".to_string())
+            ],
+            config.clans
+        );
+        assert_eq!("/secrets/api.key", config.api.key_path);
+        assert_eq!(5.0, config.api.requests_per_sec);
+        assert_eq!(20, config.api.burst);
+        assert_eq!(1, config.api.retry_policy().max_retries);
+        assert_eq!(
+            vec![StorageKind::File, StorageKind::S3],
+            config.storage.chain
+        );
+        assert_eq!(WritePolicy::Quorum, config.storage.write_policy);
+        assert_eq!("/data/data.json", config.storage.file.path);
+        assert_eq!(5, config.storage.file.backups);
+        let s3 = config.storage.s3.as_ref().unwrap();
+        assert_eq!("gold-pass", s3.bucket);
+        assert_eq!("clan-a", s3.prefix);
+        assert_eq!("storage.json", s3.key);
+        let snapshots = s3.snapshots.as_ref().unwrap();
+        assert_eq!(86400, snapshots.interval_secs);
+        assert_eq!(7, snapshots.keep);
+        assert_eq!("/data/data.sqlite", config.storage.sqlite.path);
+        assert_eq!(120, config.collector.interval_secs);
+        assert_eq!(
+            Schedule {
+                interval: Duration::from_secs(300),
+                max_backoff: Duration::from_secs(1800),
+                window: ActiveWindow::Weekdays {
+                    first: chrono::Weekday::Sat,
+                    last: chrono::Weekday::Sun
+                },
+            },
+            config.collector.schedule(Collector::Raids)
+        );
+        assert_eq!(5000, config.clan_games.max_points);
+        assert_eq!(8, config.clan_games.hour);
+        assert_eq!(
+            ActiveWindow::Days {
+                first: 19,
+                last: 26
+            },
+            config.schedule(Collector::ClanGames).window
+        );
+        assert_eq!(
+            "127.0.0.1:9090".parse::<SocketAddr>().unwrap(),
+            config.http.bind
+        );
+        assert_eq!(24.0, config.scoring.cwl.target);
+        assert_eq!(66.0, config.scoring.war.target);
+        assert_eq!(None, config.discord.token);
+        assert_eq!(Some(1234), config.discord.guild_id);
+        assert!(config.discord.verify_links);
+        assert_eq!(Some(5678), config.reminders.channel_id);
+        assert_eq!(1800, config.reminders.before_end_secs);
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let config = Config::parse("").unwrap();
+
+        assert!(config.clans.is_empty());
+        assert_eq!("api.key", config.api.key_path);
+        assert_eq!("https://api.clashofclans.com/v1", config.api.base_url);
+        assert_eq!(WritePolicy::Any, config.storage.write_policy);
+        assert_eq!("data.json", config.storage.file.path);
+        assert_eq!(3, config.storage.file.backups);
+        assert!(config.storage.s3.is_none());
+        assert_eq!(90, config.collector.interval_secs);
+        for collector in Collector::ALL {
+            assert_eq!(
+                collector.default_schedule(),
+                config.collector.schedule(collector)
+            );
+        }
+        assert_eq!(8080, config.http.bind.port());
+        assert_eq!(None, config.reminders.channel_id);
+        assert_eq!(3600, config.reminders.before_end_secs);
+    }
+
+    #[test]
+    fn parse_example() {
+        Config::parse(include_str!("../config.example.toml")).unwrap();
+    }
+
+    #[test]
+    fn parse_unknown_field() {
+        assert!(Config::parse("[discord]\ntokn = \"\"").is_err());
+    }
+
+    #[test]
+    fn parse_invalid_rate() {
+        for rate in ["0.0", "-1.0", "nan"] {
+            assert!(matches!(
+                Config::parse(&format!("[api]\nrequests_per_sec = {}", rate)),
+                Err(ConfigError::Invalid(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_invalid_clan_games() {
+        for games in [
+            "start_day = 0",
+            "end_day = 32",
+            "start_day = 28\nend_day = 22",
+            "hour = 24",
+        ] {
+            assert!(matches!(
+                Config::parse(&format!("[clan_games]\n{}", games)),
+                Err(ConfigError::Invalid(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn env_overrides() {
+        let mut config = Config::parse(
+            r#"
+[storage.file]
+path = "config.json"
+
+[discord]
+token = "config-token"
+"#,
+        )
+        .unwrap();
+
+        let env: HashMap<&str, &str> = [
+            ("API_BASE_URL", "http://localhost:8000/v1"),
+            ("STORE_PATH", "env.json"),
+            ("S3_BUCKET", "env-bucket"),
+            ("DISCORD_TOKEN", "env-token"),
+        ]
+        .into_iter()
+        .collect();
+        config.apply_overrides(|name| env.get(name).map(|v| v.to_string()));
+
+        assert_eq!("http://localhost:8000/v1", config.api.base_url);
+        assert_eq!("env.json", config.storage.file.path);
+        assert_eq!("env-bucket", config.storage.s3.unwrap().bucket);
+        assert_eq!(Some("env-token".to_string()), config.discord.token);
+        assert_eq!("api.key", config.api.key_path);
+    }
+}
@@ -1,10 +1,22 @@
-use crate::ClanStorage;
+use crate::{ClanStorage, ScoringConfig};
 
-pub struct ExcelStats {}
+pub struct ExcelStats {
+    scoring: ScoringConfig,
+    include_former: bool,
+}
 
 impl ExcelStats {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(scoring: ScoringConfig) -> Self {
+        Self {
+            scoring,
+            include_former: false,
+        }
+    }
+
+    /// Also includes the Players that left the Clan during the Season
+    pub fn with_former_members(mut self, include_former: bool) -> Self {
+        self.include_former = include_former;
+        self
     }
 
     pub fn populate_workbook(&self, stats: &ClanStorage) -> rust_xlsxwriter::Workbook {
@@ -27,13 +39,33 @@ impl ExcelStats {
         for idx in stats.raid_weekend.iter().enumerate().map(|(i, _)| i) {
             worksheet.write_string(0, column_index.next().unwrap(), format!("Raid {}", idx + 1));
         }
-        worksheet.write_string(0, column_index.next().unwrap(), "Raid Score");
+        worksheet
+            .write_string(0, column_index.next().unwrap(), "Raid Score")
+            .unwrap();
+        worksheet
+            .write_string(0, column_index.next().unwrap(), "Games Score")
+            .unwrap();
+        for header in ["Donations", "Donations Received", "Donation Score"] {
+            worksheet
+                .write_string(0, column_index.next().unwrap(), header)
+                .unwrap();
+        }
 
-        worksheet.write_string(0, column_index.next().unwrap(), "Total Score");
+        worksheet
+            .write_string(0, column_index.next().unwrap(), "Total Score")
+            .unwrap();
 
         let mut summaries: Vec<_> = stats
-            .players_summary()
-            .map(|(tag, sum)| (stats.player_names.get(&tag).unwrap(), tag, sum))
+            .players_summary(self.include_former)
+            .map(|(tag, sum)| {
+                let name = stats.player_names.get(&tag).unwrap();
+                let name = if stats.is_former_member(&tag) {
+                    format!("{} (left)", name)
+                } else {
+                    name.clone()
+                };
+                (name, tag, sum)
+            })
             .collect();
         summaries.sort_unstable_by(|(n, _, _), (n2, _, _)| n.cmp(n2));
 
@@ -41,11 +73,12 @@ impl ExcelStats {
             summaries.into_iter().enumerate().map(|(c, d)| (c + 1, d))
         {
             let row = row as u32;
+            let score = self.scoring.score(&summary);
 
             let mut column_index = (0..).into_iter();
 
             worksheet
-                .write_string(row, column_index.next().unwrap(), name)
+                .write_string(row, column_index.next().unwrap(), &name)
                 .unwrap();
 
             for w_index in 0..7 {
@@ -60,11 +93,9 @@ impl ExcelStats {
 
                 worksheet.write_number(row, column_index.next().unwrap(), stars as f64);
             }
-            worksheet.write_number(
-                row,
-                column_index.next().unwrap(),
-                (summary.cwl_stars as f64 / 21.0) * 100.0,
-            );
+            worksheet
+                .write_number(row, column_index.next().unwrap(), score.cwl)
+                .unwrap();
 
             // TODO
             // Make sure this is actually sorted and not a random order
@@ -78,11 +109,9 @@ impl ExcelStats {
 
                 worksheet.write_number(row, column_index.next().unwrap(), stars as f64);
             }
-            worksheet.write_number(
-                row,
-                column_index.next().unwrap(),
-                (summary.war_stars as f64 / 66.0) * 100.0,
-            );
+            worksheet
+                .write_number(row, column_index.next().unwrap(), score.war)
+                .unwrap();
 
             // TODO
             // Make sure this is actually sorted by time
@@ -91,17 +120,27 @@ impl ExcelStats {
 
                 worksheet.write_number(row, column_index.next().unwrap(), loot as f64);
             }
-            worksheet.write_number(
-                row,
-                column_index.next().unwrap(),
-                (summary.raid_loot as f64 / 120000.0) * 100.0,
-            );
-
-            let score = (summary.war_stars as f64 / 66.0) * 100.0
-                + (summary.cwl_stars as f64 / 21.0) * 100.0
-                + (summary.raid_loot as f64 / 120000.0) * 100.0
-                + (summary.games_score as f64 / 5000.0) * 100.0;
-            worksheet.write_number(row, column_index.next().unwrap(), score);
+            worksheet
+                .write_number(row, column_index.next().unwrap(), score.raid)
+                .unwrap();
+
+            worksheet
+                .write_number(row, column_index.next().unwrap(), score.games)
+                .unwrap();
+
+            for value in [
+                summary.donations as f64,
+                summary.donations_received as f64,
+                score.donations,
+            ] {
+                worksheet
+                    .write_number(row, column_index.next().unwrap(), value)
+                    .unwrap();
+            }
+
+            worksheet
+                .write_number(row, column_index.next().unwrap(), score.total())
+                .unwrap();
         }
 
         workbook
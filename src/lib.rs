@@ -13,55 +13,116 @@ pub use storage::*;
 mod excelstats;
 pub use excelstats::ExcelStats;
 
-pub fn parse_storage(args: &str) -> Result<Box<dyn StorageBackend>, &'static str> {
-    args.split("->")
-        .filter_map(|arg| match arg {
-            "file" => {
-                let store_path =
-                    std::env::var("STORE_PATH").unwrap_or_else(|_| "data.json".to_string());
-
-                Some(Box::new(storage::FileStorage::new(store_path)) as Box<dyn StorageBackend>)
-            }
-            "s3" => {
-                let s3_bucket = std::env::var("S3_BUCKET").expect("Missing `S3_BUCKET`");
-                let s3_access_key =
-                    std::env::var("S3_ACCESS_KEY").expect("Missing `S3_ACCESS_KEY`");
-                let s3_secret_key =
-                    std::env::var("S3_SECRET_KEY").expect("Missing `S3_SECRET_KEY`");
-                let s3_endpoint = std::env::var("S3_ENDPOINT").expect("Missing `S3_ENDPOINT`");
-
-                Some(Box::new(S3Storage::new(
-                    s3::Bucket::new(
-                        &s3_bucket,
-                        s3::Region::Custom {
-                            region: "default".to_string(),
-                            endpoint: s3_endpoint.to_string(),
-                        },
-                        s3::creds::Credentials::new(
-                            Some(&s3_access_key),
-                            Some(&s3_secret_key),
-                            None,
-                            None,
-                            None,
-                        )
-                        .unwrap(),
-                    )
-                    .unwrap()
-                    .with_path_style(),
-                )))
-            }
-            other => {
-                tracing::error!("Unknown Storage {:?}", other);
+mod scoring;
+pub use scoring::*;
+
+mod links;
+pub use links::PlayerLinks;
+
+mod reminders;
+pub use reminders::*;
+
+mod player;
+pub use player::PlayerDetails;
+
+mod ranking;
+pub use ranking::RankedPlayer;
+
+mod roster;
+pub use roster::*;
+
+mod config;
+pub use config::*;
+
+mod scheduler;
+pub use scheduler::*;
+
+mod clangames;
+pub use clangames::*;
+
+#[cfg(feature = "mock-api")]
+pub mod mockapi;
+
+/// Parses a chain of Storage Backends in the form of `file->s3` and constructs it using the
+/// given Configuration
+pub fn parse_storage(
+    args: &str,
+    config: &StorageConfig,
+) -> Result<Box<dyn StorageBackend>, &'static str> {
+    let chain: Vec<StorageKind> = args
+        .split("->")
+        .filter_map(|arg| match arg.parse() {
+            Ok(kind) => Some(kind),
+            Err(_) => {
+                tracing::error!("Unknown Storage {:?}", arg);
 
                 None
             }
         })
-        .reduce(
-            |acc: Box<dyn StorageBackend>, elem: Box<dyn StorageBackend>| {
-                Box::new(storage::Replicated::new(acc, elem)) as Box<dyn StorageBackend>
-            },
-        )
-        .ok_or("")
+        .collect();
+
+    build_storage(&chain, config)
+}
+
+/// Constructs the given chain of Storage Backends, replicating the data across all of them
+pub fn build_storage(
+    chain: &[StorageKind],
+    config: &StorageConfig,
+) -> Result<Box<dyn StorageBackend>, &'static str> {
+    chain
+        .iter()
+        .map(|kind| match kind {
+            StorageKind::File => Ok(Box::new(
+                storage::FileStorage::new(&config.file.path).with_backups(config.file.backups),
+            ) as Box<dyn StorageBackend>),
+            StorageKind::S3 => {
+                let s3_config = config.s3.as_ref().ok_or("Missing S3 Configuration")?;
+
+                let credentials = s3::creds::Credentials::new(
+                    Some(&s3_config.access_key),
+                    Some(&s3_config.secret_key),
+                    None,
+                    None,
+                    None,
+                )
+                .map_err(|_| "Invalid S3 Credentials")?;
+
+                let bucket = s3::Bucket::new(
+                    &s3_config.bucket,
+                    s3::Region::Custom {
+                        region: "default".to_string(),
+                        endpoint: s3_config.endpoint.clone(),
+                    },
+                    credentials,
+                )
+                .map_err(|_| "Invalid S3 Bucket")?
+                .with_path_style();
+
+                let mut storage = S3Storage::new(bucket)
+                    .with_prefix(&s3_config.prefix)
+                    .with_key(&s3_config.key);
+                if let Some(snapshots) = s3_config.snapshots.as_ref() {
+                    storage = storage.with_snapshots(SnapshotPolicy {
+                        interval: chrono::Duration::seconds(snapshots.interval_secs as i64),
+                        keep: snapshots.keep,
+                    });
+                }
+
+                Ok(Box::new(storage) as Box<dyn StorageBackend>)
+            }
+            StorageKind::Sqlite => {
+                Ok(Box::new(SqliteStorage::new(&config.sqlite.path)) as Box<dyn StorageBackend>)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|mut backends| match backends.len() {
+            0 => Err("No Storage configured"),
+            1 => Ok(backends.remove(0)),
+            _ => Ok(
+                Box::new(storage::Replicated::new(backends, config.write_policy))
+                    as Box<dyn StorageBackend>,
+            ),
+        })
 }
 
 #[cfg(test)]
@@ -70,7 +131,26 @@ mod tests {
 
     #[test]
     fn parse_single() {
-        std::env::set_var("S3", "");
-        parse_storage("s3").unwrap();
+        let config = StorageConfig {
+            s3: Some(S3StorageConfig {
+                bucket: "test".to_string(),
+                endpoint: "http://localhost:9000".to_string(),
+                access_key: "access".to_string(),
+                secret_key: "secret".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        parse_storage("s3", &config).unwrap();
+    }
+
+    #[test]
+    fn parse_missing_config() {
+        assert!(parse_storage("s3", &StorageConfig::default()).is_err());
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert!(parse_storage("", &StorageConfig::default()).is_err());
     }
 }
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PlayerTag;
+
+/// The Links between Discord-Users and their Clash of Clans Accounts
+///
+/// Every Player can only be linked to a single User, but a User can own multiple Players.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PlayerLinks {
+    players: HashMap<PlayerTag, u64>,
+}
+
+impl PlayerLinks {
+    /// Links the Player to the given User, returns the User the Player was previously linked to
+    pub fn link(&mut self, user: u64, player: PlayerTag) -> Option<u64> {
+        self.players.insert(player, user)
+    }
+
+    /// Removes the Link of the Player, returns the User the Player was linked to
+    pub fn unlink(&mut self, player: &PlayerTag) -> Option<u64> {
+        self.players.remove(player)
+    }
+
+    /// The User the Player is linked to
+    pub fn user(&self, player: &PlayerTag) -> Option<u64> {
+        self.players.get(player).copied()
+    }
+
+    /// All the Links between Players and Users
+    pub fn iter(&self) -> impl Iterator<Item = (&PlayerTag, u64)> + '_ {
+        self.players.iter().map(|(p, u)| (p, *u))
+    }
+
+    /// All the Players linked to the User, sorted by their Tag
+    pub fn players(&self, user: u64) -> Vec<&PlayerTag> {
+        let mut players: Vec<_> = self
+            .players
+            .iter()
+            .filter(|(_, u)| **u == user)
+            .map(|(p, _)| p)
+            .collect();
+        players.sort_by(|a, b| a.0.cmp(&b.0));
+        players
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(raw: &str) -> PlayerTag {
+        PlayerTag(raw.to_string())
+    }
+
+    #[test]
+    fn link_unlink() {
+        let mut links = PlayerLinks::default();
+
+        assert_eq!(None, links.link(1, tag("#B")));
+        assert_eq!(None, links.link(1, tag("#A")));
+        assert_eq!(None, links.link(2, tag("#C")));
+
+        assert_eq!(vec![&tag("#A"), &tag("#B")], links.players(1));
+        assert_eq!(Some(2), links.user(&tag("#C")));
+
+        // Relinking moves the Player to the new User
+        assert_eq!(Some(2), links.link(1, tag("#C")));
+        assert!(links.players(2).is_empty());
+
+        assert_eq!(Some(1), links.unlink(&tag("#A")));
+        assert_eq!(None, links.unlink(&tag("#A")));
+        assert_eq!(vec![&tag("#B"), &tag("#C")], links.players(1));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let mut links = PlayerLinks::default();
+        links.link(1234, tag("#A"));
+
+        let serialized = serde_json::to_string(&links).unwrap();
+        assert_eq!(links, serde_json::from_str(&serialized).unwrap());
+    }
+}
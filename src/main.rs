@@ -1,37 +1,21 @@
 #![feature(iter_intersperse)]
 
-use std::borrow::Cow;
-use std::collections::BTreeMap;
-use std::env;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use gold_pass_bot::{
-    ClanTag, ExcelStats, PlayerSummary, RaidMember, RaidWeekendStats, Season, Storage,
-};
-use serenity::async_trait;
-use serenity::framework::standard::macros::{command, group};
-use serenity::framework::standard::{CommandResult, StandardFramework};
-use serenity::model::channel::Message;
-use serenity::model::prelude::AttachmentType;
+use gold_pass_bot::{ClanTag, Collector, Config, OpenWar, Reminders, Storage, StorageError};
+use serenity::model::id::{ChannelId, GuildId};
 use serenity::prelude::*;
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
 
 use arc_swap::ArcSwap;
 
-#[group]
-#[commands(stats, export)]
-struct General;
+mod commands;
+use commands::{ApiClient, ClanStates, Handler, Links, Scoring, TrackedClans};
 
-struct Handler;
-
-struct ClanStates;
-impl TypeMapKey for ClanStates {
-    type Value = Arc<ArcSwap<(Storage, u64)>>;
-}
-
-#[async_trait]
-impl EventHandler for Handler {}
+/// The currently running Wars, as last reported by the War- and CWL-Collectors of each Clan
+type OpenWars = HashMap<(ClanTag, Collector), Vec<OpenWar>>;
 
 static REGISTRY: once_cell::sync::Lazy<prometheus::Registry> =
     once_cell::sync::Lazy::new(|| prometheus::Registry::new());
@@ -43,164 +27,249 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer().with_ansi(false));
     tracing::subscriber::set_global_default(layers).unwrap();
 
+    let common_args = [
+        clap::Arg::new("config")
+            .long("config")
+            .value_names(["file"]),
+        clap::Arg::new("storage")
+            .long("storage")
+            .value_names(["storage-target"]),
+        clap::Arg::new("clan")
+            .long("clan")
+            .value_names(["clan-tag"])
+            .action(clap::ArgAction::Append)
+            .value_parser(|raw: &str| raw.parse::<ClanTag>()),
+    ];
+
     let args = clap::Command::new("Gold-Pass-Bot")
+        .subcommand(clap::Command::new("bot").args(common_args.clone()))
         .subcommand(
-            clap::Command::new("bot").arg(
-                clap::Arg::new("storage")
-                    .long("storage")
-                    .value_names(["storage-target"]),
-            ),
+            clap::Command::new("backfill")
+                .about("Fills in past Seasons from the full War-Log and Raid history")
+                .args(common_args),
         )
         .get_matches();
 
-    let mut storage_backend = args
-        .subcommand_matches("bot")
-        .expect("Missing subcommand")
-        .get_one::<String>("storage")
-        .map(|arg: &String| gold_pass_bot::parse_storage(&arg))
-        .expect("Missing Storage")
-        .unwrap();
+    let (subcommand, bot_args) = args.subcommand().expect("Missing subcommand");
+
+    let mut config = match bot_args.get_one::<String>("config") {
+        Some(path) => Config::load(path).expect("Loading Configuration"),
+        None => Config::default(),
+    };
+    config.apply_env();
 
-    let api_path = std::env::var("API_PATH").unwrap_or_else(|_| "api.key".to_string());
+    if let Some(clans) = bot_args.get_many::<ClanTag>("clan") {
+        config.clans = clans.cloned().collect();
+    }
+    if config.clans.is_empty() {
+        panic!("At least one Clan needs to be configured using `--clan` or the Configuration");
+    }
+    tracing::info!("Tracking Clans: {:?}", config.clans);
+    let tracked_clans = Arc::new(config.clans.clone());
 
-    #[cfg(not(debug_assertions))]
-    let prefix = "!";
-    #[cfg(debug_assertions)]
-    let prefix = "+";
-    let framework = StandardFramework::new()
-        .configure(|c| c.prefix(prefix))
-        .group(&GENERAL_GROUP);
+    let mut storage_backend = match bot_args.get_one::<String>("storage") {
+        Some(arg) => gold_pass_bot::parse_storage(arg, &config.storage),
+        None => gold_pass_bot::build_storage(&config.storage.chain, &config.storage),
+    }
+    .expect("Missing Storage");
 
-    // Login with a bot token from the environment
-    let token = env::var("DISCORD_TOKEN")
-        .expect("Discord Token should be set using the `DISCORD_TOKEN` environment variable");
-    let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
-    let mut client = Client::builder(token, intents)
-        .event_handler(Handler)
-        .framework(framework)
-        .await
-        .expect("Error creating client");
+    let api_path = config.api.key_path.clone();
+    let publish_interval = Duration::from_secs(config.collector.interval_secs);
 
     let elapsed = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
-    let storage = Storage::load(storage_backend.as_mut())
-        .await
-        .unwrap_or_else(|_| Storage::empty());
+    let storage = match Storage::load(storage_backend.as_mut()).await {
+        Ok(s) => s,
+        Err(StorageError::NotFound) => {
+            tracing::info!("No existing Storage found, starting with an empty one");
+            Storage::empty()
+        }
+        Err(e) => panic!(
+            "Loading the Storage failed, refusing to start to not overwrite it: {:?}",
+            e
+        ),
+    };
     let shared_storage = Arc::new(ArcSwap::new(Arc::new((storage.clone(), elapsed))));
+    let shared_links = Arc::new(ArcSwap::new(Arc::new(storage.links().clone())));
+
+    let raw_key = tokio::fs::read_to_string(api_path).await.unwrap();
+    let key = raw_key
+        .as_str()
+        .strip_suffix("\n")
+        .unwrap_or(raw_key.as_str());
+    let api_client = Arc::new(
+        gold_pass_bot::Client::new(key.to_string())
+            .with_base_url(&config.api.base_url)
+            .with_timeout(Duration::from_secs(config.api.timeout_secs))
+            .with_rate_limit(config.api.rate_limiter())
+            .with_retries(config.api.retry_policy()),
+    );
+
+    if subcommand == "backfill" {
+        backfill(
+            &api_client,
+            &tracked_clans,
+            storage,
+            storage_backend.as_mut(),
+        )
+        .await;
+        return;
+    }
+
+    // Login with a bot token from the configuration or environment
+    let token = config.discord.token.clone().expect(
+        "Discord Token should be set using the `DISCORD_TOKEN` environment variable or the Configuration",
+    );
+    let intents = GatewayIntents::non_privileged();
+    let mut client = Client::builder(token, intents)
+        .event_handler(Handler {
+            guild_id: config.discord.guild_id.map(GuildId),
+            verify_links: config.discord.verify_links,
+        })
+        .await
+        .expect("Error creating client");
+
     {
         let mut data = client.data.write().await;
         data.insert::<ClanStates>(shared_storage.clone());
+        data.insert::<TrackedClans>(tracked_clans.clone());
+        data.insert::<Scoring>(Arc::new(config.scoring.clone()));
+        data.insert::<Links>(shared_links.clone());
+        data.insert::<ApiClient>(api_client.clone());
     }
 
-    tokio::spawn(async move {
-        let raw_key = tokio::fs::read_to_string(api_path).await.unwrap();
-        let key = raw_key
-            .as_str()
-            .strip_suffix("\n")
-            .unwrap_or(raw_key.as_str());
-        let client = gold_pass_bot::Client::new(key.to_string());
-
-        let alfie_tag = "#2L99VLJ9P";
-
-        let mut storage = storage;
-        storage.register_clan(ClanTag(alfie_tag.to_string()));
-
-        let error_counter =
-            prometheus::Counter::new("api_errors", "The Number of errors returned by the API")
-                .unwrap();
-
-        once_cell::sync::Lazy::force(&REGISTRY)
-            .register(Box::new(error_counter.clone()))
-            .unwrap();
-
-        loop {
-            let season = Season::current();
-
-            for tag in [ClanTag(alfie_tag.to_string())] {
-                let update_span = tracing::span!(tracing::Level::INFO, "UpdateClanStats");
-                let _tmp = update_span.enter();
-
-                tracing::debug!("Updating Clan Stats: {:?}", tag);
-
-                let clan_season_stats = match storage.get_mut(&tag, &season) {
-                    Some(s) => s,
-                    None => {
-                        tracing::error!(
-                            "Getting Stats entry for Clan {:?} and Season {:?}",
-                            tag,
-                            season
-                        );
-                        continue;
-                    }
-                };
-
-                if let Err(e) = gold_pass_bot::update_names(&client, &tag, clan_season_stats).await
-                {
-                    error_counter.inc();
-                }
-
-                if let Err(e) = gold_pass_bot::update_war(&client, &tag, &mut storage).await {
-                    error_counter.inc();
-                }
+    let http = client.cache_and_http.http.clone();
+    let reminder_channel = config.reminders.channel_id.map(ChannelId);
+    let reminder_before = chrono::Duration::seconds(config.reminders.before_end_secs as i64);
 
-                if let Err(e) = gold_pass_bot::update_cwl(&client, &tag, &mut storage).await {
-                    error_counter.inc();
-                }
+    let mut storage = storage;
+    for tag in tracked_clans.iter() {
+        storage.register_clan(tag.clone());
+    }
+    let storage = Arc::new(Mutex::new(storage));
+    let open_wars: Arc<Mutex<OpenWars>> = Default::default();
+    let clan_games = Arc::new(config.clan_games.clone());
 
-                if let Err(e) = gold_pass_bot::update_clan_games(&client, &tag, &mut storage).await
-                {
-                    error_counter.inc();
-                }
+    let error_counter = prometheus::IntCounterVec::new(
+        prometheus::Opts::new("api_errors", "The Number of errors returned by the API"),
+        &["collector"],
+    )
+    .unwrap();
+    once_cell::sync::Lazy::force(&REGISTRY)
+        .register(Box::new(error_counter.clone()))
+        .unwrap();
 
-                match client.captial_raid_seasons(&tag).await {
-                    Ok(raid_res) => {
-                        for raid in raid_res.items {
-                            tracing::debug!("Start-Time: {:?}", raid.startTime);
-                            let members = match raid.members {
-                                Some(m) => m
-                                    .into_iter()
-                                    .map(|member| {
-                                        (
-                                            member.tag,
-                                            RaidMember {
-                                                looted: member.capitalResourcesLooted,
-                                            },
+    for tag in tracked_clans.iter() {
+        for collector in Collector::ALL {
+            let schedule = config.schedule(collector);
+            let name = format!("{:?} of {}", collector, tag.0);
+            let errors = error_counter.with_label_values(&[&format!("{:?}", collector)]);
+
+            let client = api_client.clone();
+            let tag = tag.clone();
+            let storage = storage.clone();
+            let open_wars = open_wars.clone();
+            let clan_games = clan_games.clone();
+
+            tokio::spawn(async move {
+                gold_pass_bot::run_scheduled(&name, schedule, || {
+                    let (client, tag, storage, open_wars, clan_games, errors) = (
+                        client.clone(),
+                        tag.clone(),
+                        storage.clone(),
+                        open_wars.clone(),
+                        clan_games.clone(),
+                        errors.clone(),
+                    );
+
+                    async move {
+                        let result = match collector {
+                            Collector::Names => {
+                                gold_pass_bot::update_names(&client, &tag, &storage).await
+                            }
+                            Collector::War => {
+                                let wars = gold_pass_bot::update_war(&client, &tag, &storage)
+                                    .await
+                                    .map(|w| w.into_iter().collect());
+                                let reconciled =
+                                    gold_pass_bot::reconcile_wars(&client, &tag, &storage).await;
+
+                                wars.and_then(|w| reconciled.map(|_| w)).map(|w| {
+                                    open_wars.lock().unwrap().insert((tag, collector), w);
+                                })
+                            }
+                            Collector::Cwl => gold_pass_bot::update_cwl(&client, &tag, &storage)
+                                .await
+                                .map(|w| {
+                                    open_wars.lock().unwrap().insert((tag, collector), w);
+                                }),
+                            Collector::ClanGames => {
+                                match clan_games.event(&gold_pass_bot::Season::current()) {
+                                    Some(event) => {
+                                        gold_pass_bot::update_clan_games(
+                                            &client, &tag, &storage, &event,
                                         )
-                                    })
-                                    .collect(),
-                                None => {
-                                    tracing::trace!(
-                                        "Skipping weekend, because there is no member list"
-                                    );
-
-                                    continue;
+                                        .await
+                                    }
+                                    None => {
+                                        tracing::warn!(
+                                            "No Clan Games Event this Season with {:?}",
+                                            clan_games
+                                        );
+                                        Ok(())
+                                    }
                                 }
-                            };
-
-                            let start_time = raid.startTime;
-
-                            let clan_season_stats =
-                                storage.get_mut(&tag, &raid.startTime.into()).unwrap();
-
-                            clan_season_stats.raid_weekend.insert(
-                                start_time.clone(),
-                                RaidWeekendStats {
-                                    start_time,
-                                    members,
-                                },
-                            );
+                            }
+                            Collector::Raids => {
+                                gold_pass_bot::update_raids(&client, &tag, &storage).await
+                            }
+                        };
+
+                        if result.is_err() {
+                            errors.inc();
                         }
+                        result
                     }
-                    Err(e) => {
-                        error_counter.inc();
+                })
+                .await;
+            });
+        }
+    }
 
-                        tracing::error!("Error loading Capital Raid Seasons: {:?}", e);
-                    }
-                };
+    tokio::spawn(async move {
+        let mut reminders = Reminders::default();
 
-                drop(_tmp);
+        loop {
+            tokio::time::sleep(publish_interval).await;
+
+            // The Links are managed by the Discord Commands
+            let storage = {
+                let mut storage = storage.lock().unwrap();
+                storage.set_links(shared_links.load().as_ref().clone());
+                storage.clone()
+            };
+
+            if let Some(channel) = reminder_channel {
+                let now = chrono::Utc::now();
+                let wars: Vec<OpenWar> = open_wars
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .flatten()
+                    .cloned()
+                    .collect();
+
+                for war in reminders.due(wars, now, reminder_before) {
+                    tracing::info!("Reminding missed Attacks for {:?}", war.clan);
+
+                    for content in war.reminder_messages(storage.links(), now) {
+                        if let Err(e) = channel.say(&http, content).await {
+                            tracing::error!("Sending Reminder: {:?}", e);
+                        }
+                    }
+                }
             }
 
             let elapsed = SystemTime::now()
@@ -214,15 +283,14 @@ async fn main() {
             }
 
             tracing::info!("Done Updating Stats");
-
-            tokio::time::sleep(Duration::from_secs(90)).await;
         }
     });
 
+    let http_bind = config.http.bind;
     tokio::spawn(async move {
         let app = axum::Router::new().route("/metrics", axum::routing::get(metrics));
 
-        axum::Server::bind(&"0.0.0.0:8080".parse().unwrap())
+        axum::Server::bind(&http_bind)
             .serve(app.into_make_service())
             .await
             .unwrap();
@@ -234,187 +302,39 @@ async fn main() {
     }
 }
 
-fn generate_batches(
-    player_count: usize,
-    header_padding_width: usize,
-    padding_width: usize,
-    summaries: BTreeMap<&String, PlayerSummary>,
-    timestamp: u64,
-) -> Vec<String> {
-    let mut summary_iter = summaries
-        .iter()
-        .map(|(name, sum)| {
-            format!(
-                "{:width$}|  {:2} |   {:2} | {:5} |    {:2}",
-                name,
-                sum.cwl_stars,
-                sum.war_stars,
-                sum.raid_loot,
-                sum.games_score,
-                width = padding_width
-            )
-        })
-        .peekable();
-    let line_width = format!(
-        "{:width$}|  {:2} |   {:2} | {:5} |    {:2}",
-        "",
-        0,
-        0,
-        0,
-        0,
-        width = padding_width
-    )
-    .len();
-
-    let players_per_line = 1800 / line_width;
-    (0..(player_count / players_per_line + 1))
-        .map(|batch| {
-            let summary: String = core::iter::once(format!(
-                "```Player Tag {:width$}| CWL | Wars | Raids | Games",
-                ' ',
-                width = header_padding_width
-            ))
-            .chain(core::iter::once(
-                core::iter::repeat('-')
-                    .take(40 + padding_width.saturating_sub(11))
-                    .collect(),
-            ))
-            .chain(summary_iter.by_ref().take(players_per_line))
-            .chain(core::iter::once("```".to_string()))
-            .intersperse("\n".to_string())
-            .collect();
-
-            format!(
-                "{}\nTimestamp: {}\n{}/{}",
-                summary,
-                timestamp,
-                batch + 1,
-                player_count / players_per_line + 1
-            )
-        })
-        .collect()
-}
-
-#[command]
-async fn stats(ctx: &Context, msg: &Message) -> CommandResult {
-    let guard = ctx.data.read().await;
-    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
-
-    let stats_guard = storage.load();
-    let (stats, timestamp) = stats_guard.as_ref();
-
-    let alfie_tag = ClanTag("#2L99VLJ9P".to_string());
-
-    let season = Season::current();
-    tracing::trace!("Displaying stats for season: {:?}", season);
-
-    let clan_stats = stats.get(&alfie_tag, &season).expect("");
-
-    let player_count = clan_stats.players_summary().count();
-    tracing::trace!("Sending Summary for {} Players", player_count);
-
-    let max_name_length = clan_stats
-        .players_summary()
-        .map(|(n, _)| {
-            clan_stats
-                .player_names
-                .get(&n)
-                .map(|n| n.len())
-                .unwrap_or(0)
-        })
-        .max()
-        .unwrap_or(0);
-
-    let padding_width = 11.max(max_name_length);
-    let header_padding_width = padding_width.saturating_sub(11);
-
-    let player_summaries: BTreeMap<_, _> = clan_stats
-        .players_summary()
-        .map(|(tag, v)| (clan_stats.player_names.get(&tag).unwrap(), v))
-        .collect();
-
-    if let Err(e) = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.content(format!("Stats for {:02}-{}", season.month, season.year))
-        })
-        .await
-    {
-        tracing::error!("Sending Message {:?}", e);
+/// Backfills every Clan once and saves the Storage, used by the `backfill` Subcommand
+async fn backfill(
+    client: &gold_pass_bot::Client,
+    clans: &[ClanTag],
+    mut storage: Storage,
+    backend: &mut dyn gold_pass_bot::StorageBackend,
+) {
+    for tag in clans {
+        storage.register_clan(tag.clone());
     }
+    let storage = Mutex::new(storage);
 
-    let batches = generate_batches(
-        player_count,
-        header_padding_width,
-        padding_width,
-        player_summaries,
-        *timestamp,
-    );
-
-    for (batch, content) in batches.into_iter().enumerate() {
-        tracing::trace!("Sending Batch: {}", batch);
-
-        tracing::trace!("Summary message length: {:?}", content.len());
-
-        if let Err(e) = msg
-            .channel_id
-            .send_message(&ctx.http, |m| m.content(content))
-            .await
-        {
-            tracing::error!("Sending Response: {:?}", e);
+    for tag in clans {
+        match gold_pass_bot::backfill(client, tag, &storage).await {
+            Ok(report) => tracing::info!("Backfilled {}: {:?}", tag.0, report),
+            Err(e) => tracing::error!("Backfilling {}: {:?}", tag.0, e),
         }
     }
 
-    Ok(())
-}
-
-#[command]
-async fn export(ctx: &Context, msg: &Message) -> CommandResult {
-    let guard = ctx.data.read().await;
-    let storage: &Arc<ArcSwap<_>> = guard.get::<ClanStates>().unwrap();
-
-    let stats_guard = storage.load();
-    let (stats, _timestamp) = stats_guard.as_ref(); // TODO
-
-    let alfie_tag = ClanTag("#2L99VLJ9P".to_string());
-
-    let current_season = Season::current();
-    let last_season = current_season.previous();
-
-    tracing::trace!(
-        "Displaying stats for seasons: {:?}, {:?}",
-        current_season,
-        last_season
-    );
-
-    let files = [current_season, last_season].map(|season| {
-        let clan_stats = stats.get(&alfie_tag, &season).expect("");
-
-        let mut excel_book = ExcelStats::new().populate_workbook(clan_stats);
-        let content = excel_book.save_to_buffer().unwrap();
-
-        AttachmentType::Bytes {
-            data: Cow::Owned(content),
-            filename: format!("Tracker - {}-{}.xlsx", season.month, season.year),
-        }
-    });
-
-    if let Err(e) = msg
-        .channel_id
-        .send_files(&ctx.http, files, |m| m.content("Populated Spreadsheet"))
+    storage
+        .into_inner()
+        .unwrap()
+        .save(backend)
         .await
-    {
-        tracing::error!("Sending Excel Stats: {:?}", e);
-    }
-
-    Ok(())
+        .expect("Saving Storage");
 }
 
 async fn metrics() -> String {
     let reg = once_cell::sync::Lazy::force(&REGISTRY);
 
     let encoder = prometheus::TextEncoder::new();
-    let metrics_family = reg.gather();
+    let mut metrics_family = reg.gather();
+    metrics_family.extend(prometheus::gather());
     let encoded = encoder.encode_to_string(&metrics_family).unwrap();
 
     encoded
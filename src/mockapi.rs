@@ -0,0 +1,228 @@
+//! A Server imitating the Clash of Clans API by serving recorded Responses, allows running the
+//! Collectors against it without access to the real API.
+//!
+//! The Response for a Path is loaded from the JSON file with the same Path below the Fixture
+//! Directory, without the `#` of the Tags, e.g. `/clans/%232L99VLJ9P/currentwar` is served from
+//! `<dir>/clans/2L99VLJ9P/currentwar.json`. Paths without a Fixture return a 404 like the real API.
+//!
+//! Pages selected using an `after` Cursor are served from `<file>@<cursor>.json`, e.g.
+//! `/clans/%232L99VLJ9P/warlog?after=abc` from `<dir>/clans/2L99VLJ9P/warlog@abc.json`. The
+//! Cursors are Base64, so `+` and `/` are replaced by `-` and `_` in the File-Name, like in the
+//! URL-safe Alphabet.
+
+use std::{
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+
+/// Serves the Fixtures in the given Directory
+pub fn router<P>(dir: P) -> axum::Router
+where
+    P: Into<PathBuf>,
+{
+    axum::Router::new()
+        .fallback(serve_fixture)
+        .with_state(Arc::new(dir.into()))
+}
+
+/// Starts serving the Fixtures on the given Address in the background, returns the Address the
+/// Server is listening on, which allows binding to port 0 in Tests
+pub fn spawn<P>(dir: P, addr: SocketAddr) -> SocketAddr
+where
+    P: Into<PathBuf>,
+{
+    let server = axum::Server::bind(&addr).serve(router(dir).into_make_service());
+    let addr = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            tracing::error!("Mock API: {:?}", e);
+        }
+    });
+
+    addr
+}
+
+/// The File containing the Fixture for the Request-Path and Cursor, `None` if the Path tries to
+/// escape the Directory
+fn fixture_path(dir: &Path, path: &str, after: Option<&str>) -> Option<PathBuf> {
+    let path = path.replace("%23", "").replace('#', "");
+    let relative = Path::new(path.trim_matches('/'));
+
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let mut file = dir.join(relative).into_os_string();
+    if let Some(after) = after {
+        let cursor = after
+            .chars()
+            .map(|c| match c {
+                '+' => Some('-'),
+                '/' => Some('_'),
+                c if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '=') => Some(c),
+                _ => None,
+            })
+            .collect::<Option<String>>()?;
+        file.push(format!("@{}", cursor));
+    }
+    file.push(".json");
+    Some(PathBuf::from(file))
+}
+
+/// Decodes a percent-encoded Query-Value, `None` if it is not valid
+fn decode_query(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut raw = value.bytes();
+    while let Some(b) = raw.next() {
+        if b == b'%' {
+            let hex = [raw.next()?, raw.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+fn error(status: StatusCode, reason: &str) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/json")],
+        serde_json::json!({ "reason": reason }).to_string(),
+    )
+        .into_response()
+}
+
+async fn serve_fixture(
+    State(dir): State<Arc<PathBuf>>,
+    headers: axum::http::HeaderMap,
+    uri: Uri,
+) -> Response {
+    if !headers.contains_key(header::AUTHORIZATION) {
+        return error(StatusCode::FORBIDDEN, "accessDenied");
+    }
+
+    let after = match uri
+        .query()
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|param| param.strip_prefix("after="))
+        .map(decode_query)
+    {
+        Some(None) => return error(StatusCode::BAD_REQUEST, "badRequest"),
+        Some(Some(a)) => Some(a),
+        None => None,
+    };
+
+    let file = match fixture_path(&dir, uri.path(), after.as_deref()) {
+        Some(f) => f,
+        None => return error(StatusCode::BAD_REQUEST, "badRequest"),
+    };
+
+    match tokio::fs::read(&file).await {
+        Ok(content) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            content,
+        )
+            .into_response(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::debug!("No Fixture for {:?}", uri.path());
+            error(StatusCode::NOT_FOUND, "notFound")
+        }
+        Err(e) => {
+            tracing::error!("Reading Fixture {:?}: {:?}", file, e);
+            error(StatusCode::INTERNAL_SERVER_ERROR, "unknownException")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixture_paths() {
+        let dir = Path::new("fixtures/api");
+
+        assert_eq!(
+            Some(PathBuf::from(
+                "fixtures/api/clans/2L99VLJ9P/currentwar.json"
+            )),
+            fixture_path(dir, "/clans/%232L99VLJ9P/currentwar", None)
+        );
+        assert_eq!(
+            Some(PathBuf::from("fixtures/api/players/P2Y8QJ0L.json")),
+            fixture_path(dir, "/players/#P2Y8QJ0L", None)
+        );
+        assert_eq!(
+            Some(PathBuf::from(
+                "fixtures/api/clans/2L99VLJ9P/warlog@abc.json"
+            )),
+            fixture_path(dir, "/clans/%232L99VLJ9P/warlog", Some("abc"))
+        );
+        assert_eq!(
+            Some(PathBuf::from(
+                "fixtures/api/clans/2L99VLJ9P/warlog@a-b_c=.json"
+            )),
+            fixture_path(dir, "/clans/%232L99VLJ9P/warlog", Some("a+b/c="))
+        );
+        assert_eq!(None, fixture_path(dir, "/clans/../../secret", None));
+        assert_eq!(None, fixture_path(dir, "/clans/warlog", Some("../secret")));
+    }
+
+    #[test]
+    fn decode_queries() {
+        assert_eq!(Some("a+b/c=".to_string()), decode_query("a%2Bb%2Fc%3D"));
+        assert_eq!(Some("abc".to_string()), decode_query("abc"));
+        assert_eq!(None, decode_query("abc%2"));
+        assert_eq!(None, decode_query("%zz"));
+    }
+
+    #[tokio::test]
+    async fn base64_cursor() {
+        let dir = std::env::temp_dir().join(format!("gold-pass-mockapi-{}", std::process::id()));
+        let clan_dir = dir.join("clans/2L99VLJ9P");
+        std::fs::create_dir_all(&clan_dir).unwrap();
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/api/clans/2L99VLJ9P");
+        let first = std::fs::read_to_string(fixtures.join("warlog.json")).unwrap();
+        std::fs::write(
+            clan_dir.join("warlog.json"),
+            first.replace("eyJwb3MiOjF9", "eyJwb3MiOjF9+/="),
+        )
+        .unwrap();
+        std::fs::copy(
+            fixtures.join("warlog@eyJwb3MiOjF9.json"),
+            clan_dir.join("warlog@eyJwb3MiOjF9-_=.json"),
+        )
+        .unwrap();
+
+        let addr = spawn(&dir, SocketAddr::from(([127, 0, 0, 1], 0)));
+        let client = crate::Client::new("test-key").with_base_url(format!("http://{}", addr));
+
+        let clan = crate::ClanTag("#2L99VLJ9P".to_string());
+        let entries: Vec<_> = futures::TryStreamExt::try_collect(client.war().all_logs(&clan))
+            .await
+            .unwrap();
+        let end_times: Vec<_> = entries.iter().map(|e| e.end_time.as_str()).collect();
+        assert_eq!(
+            vec!["20230812T070000.000Z", "20230805T070000.000Z"],
+            end_times
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
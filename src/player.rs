@@ -0,0 +1,227 @@
+use crate::{ClanStorage, PlayerSummary, PlayerTag, Time, WarAttack};
+
+/// All the Stats of a single Player in a Season
+#[derive(Debug)]
+pub struct PlayerDetails<'s> {
+    pub tag: PlayerTag,
+    pub name: Option<&'s str>,
+    /// The Attacks for every CWL round, `None` if the Player was not part of the lineup
+    pub cwl: Vec<Option<&'s [WarAttack]>>,
+    /// The Attacks in every regular War the Player participated in
+    pub wars: Vec<(Time, &'s [WarAttack])>,
+    /// The Number of Wars in the Season that are only known from the War-Log, so it is unknown
+    /// whether the Player participated in them
+    pub summary_only_wars: usize,
+    /// The Loot of every Raid Weekend the Player participated in
+    pub raids: Vec<(Time, usize)>,
+    /// The Points earned in the Clan Games
+    pub games: Option<usize>,
+    pub summary: PlayerSummary,
+}
+
+impl ClanStorage {
+    /// Finds a Player either by their Tag or their Name, Names are compared case insensitive
+    pub fn find_player(&self, query: &str) -> Option<PlayerTag> {
+        if let Ok(tag) = query.parse::<PlayerTag>() {
+            if self.player_names.contains_key(&tag) {
+                return Some(tag);
+            }
+        }
+
+        let query = query.trim();
+        let mut matches = self
+            .player_names
+            .iter()
+            .filter(|(_, name)| name.eq_ignore_ascii_case(query))
+            .map(|(tag, _)| tag.clone());
+
+        match (matches.next(), matches.next()) {
+            (Some(tag), None) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Collects all the Stats of the given Player
+    pub fn player_details(&self, tag: &PlayerTag) -> PlayerDetails<'_> {
+        let cwl = self
+            .cwl
+            .wars
+            .iter()
+            .map(|war| war.members.get(tag).map(|m| m.attacks.as_slice()))
+            .collect();
+
+        let wars = self
+            .wars
+            .values()
+            .filter_map(|war| {
+                war.members
+                    .get(tag)
+                    .map(|m| (war.start_time, m.attacks.as_slice()))
+            })
+            .collect();
+        let summary_only_wars = self.wars.values().filter(|war| war.summary_only).count();
+
+        let raids = self
+            .raid_weekend
+            .values()
+            .filter_map(|raid| raid.members.get(tag).map(|m| (raid.start_time, m.looted)))
+            .collect();
+
+        let games = self.games.get(tag).map(|g| g.score());
+
+        let summary = self.player_summary(tag);
+
+        PlayerDetails {
+            tag: tag.clone(),
+            name: self.player_names.get(tag).map(|n| n.as_str()),
+            cwl,
+            wars,
+            summary_only_wars,
+            raids,
+            games,
+            summary,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        CwlWarStats, MemberWarStats, PlayerDonationStats, PlayerGamesStats, RaidMember,
+        RaidWeekendStats, WarStats,
+    };
+
+    fn time(day: usize) -> Time {
+        Time {
+            year: 2023,
+            month: 8,
+            day,
+        }
+    }
+
+    fn attack(stars: usize) -> WarAttack {
+        WarAttack {
+            destruction: 100,
+            stars,
+            duration: 60,
+            order: None,
+        }
+    }
+
+    fn tag(raw: &str) -> PlayerTag {
+        PlayerTag(raw.to_string())
+    }
+
+    fn storage() -> ClanStorage {
+        let mut storage = ClanStorage::default();
+        storage.player_names.insert(tag("#AB"), "First".to_string());
+        storage
+            .player_names
+            .insert(tag("#CD"), "Second".to_string());
+        storage
+            .player_names
+            .insert(tag("#EF"), "second".to_string());
+
+        storage.cwl.wars = vec![
+            CwlWarStats {
+                members: [(
+                    tag("#AB"),
+                    MemberWarStats {
+                        attacks: vec![attack(3)],
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            },
+            CwlWarStats {
+                members: HashMap::new(),
+            },
+        ];
+        storage.wars.insert(
+            time(10),
+            WarStats {
+                start_time: time(10),
+                end_time: None,
+                finished: true,
+                summary: None,
+                summary_only: false,
+                members: [(
+                    tag("#AB"),
+                    MemberWarStats {
+                        attacks: vec![attack(2), attack(1)],
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            },
+        );
+        storage.raid_weekend.insert(
+            time(4),
+            RaidWeekendStats {
+                start_time: time(4),
+                members: [(tag("#AB"), RaidMember { looted: 20000 })]
+                    .into_iter()
+                    .collect(),
+            },
+        );
+        storage.games.insert(
+            tag("#AB"),
+            PlayerGamesStats {
+                start_score: Some(1000),
+                end_score: 5000,
+                late: false,
+                monthly_baseline: false,
+            },
+        );
+        storage.donations.insert(
+            tag("#AB"),
+            PlayerDonationStats {
+                donated: 250,
+                received: 100,
+                last_donations: 900,
+                last_received: 300,
+            },
+        );
+
+        storage
+    }
+
+    #[test]
+    fn find_player() {
+        let storage = storage();
+
+        assert_eq!(Some(tag("#AB")), storage.find_player("ab"));
+        assert_eq!(Some(tag("#AB")), storage.find_player("first"));
+        assert_eq!(None, storage.find_player("#XYZ"));
+        // Ambiguous Names are not resolved
+        assert_eq!(None, storage.find_player("Second"));
+    }
+
+    #[test]
+    fn details() {
+        let storage = storage();
+        let details = storage.player_details(&tag("#AB"));
+
+        assert_eq!(Some("First"), details.name);
+        assert_eq!(2, details.cwl.len());
+        assert_eq!(1, details.cwl[0].unwrap().len());
+        assert!(details.cwl[1].is_none());
+        assert_eq!(0, details.summary_only_wars);
+        assert_eq!(vec![(time(4), 20000)], details.raids);
+        assert_eq!(Some(4000), details.games);
+        assert_eq!(
+            PlayerSummary {
+                cwl_stars: 3,
+                war_stars: 3,
+                raid_loot: 20000,
+                games_score: 4000,
+                donations: 250,
+                donations_received: 100,
+            },
+            details.summary
+        );
+    }
+}
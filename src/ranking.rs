@@ -0,0 +1,179 @@
+use std::cmp::Ordering;
+
+use crate::{ClanStorage, PlayerSummary, PlayerTag, Score, ScoringConfig, Time};
+
+/// A single Entry in the Leaderboard of a Clan
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedPlayer {
+    /// The 1-based Rank of the Player
+    pub rank: usize,
+    pub tag: PlayerTag,
+    pub summary: PlayerSummary,
+    pub score: Score,
+    /// The Start of the War and the Order of the earliest regular War Attack of the Player
+    pub first_attack: Option<(Time, usize)>,
+}
+
+impl ClanStorage {
+    /// Ranks all the Players of the Season based on their Score.
+    ///
+    /// Ties are broken deterministically by
+    /// 1. the total Score
+    /// 2. the Stars gained in regular Wars
+    /// 3. the earliest regular War Attack, Players that attacked earlier are ranked higher
+    /// 4. the Player-Tag
+    pub fn leaderboard(&self, scoring: &ScoringConfig) -> Vec<RankedPlayer> {
+        let mut players: Vec<_> = self
+            .players_summary(false)
+            .map(|(tag, summary)| {
+                let score = scoring.score(&summary);
+                let first_attack = self.first_attack(&tag);
+
+                RankedPlayer {
+                    rank: 0,
+                    tag,
+                    summary,
+                    score,
+                    first_attack,
+                }
+            })
+            .collect();
+
+        players.sort_by(compare_players);
+
+        for (idx, player) in players.iter_mut().enumerate() {
+            player.rank = idx + 1;
+        }
+
+        players
+    }
+
+    fn first_attack(&self, tag: &PlayerTag) -> Option<(Time, usize)> {
+        self.wars
+            .values()
+            .filter_map(|war| {
+                let member = war.members.get(tag)?;
+                member
+                    .attacks
+                    .iter()
+                    .map(|attack| (war.start_time, attack.order.unwrap_or(usize::MAX)))
+                    .min()
+            })
+            .min()
+    }
+}
+
+fn compare_players(first: &RankedPlayer, second: &RankedPlayer) -> Ordering {
+    second
+        .score
+        .total()
+        .total_cmp(&first.score.total())
+        .then_with(|| second.summary.war_stars.cmp(&first.summary.war_stars))
+        .then_with(|| match (&first.first_attack, &second.first_attack) {
+            (Some(f), Some(s)) => f.cmp(s),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+        .then_with(|| first.tag.0.cmp(&second.tag.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{MemberWarStats, RaidMember, RaidWeekendStats, WarAttack, WarStats};
+
+    fn time(day: usize) -> Time {
+        Time {
+            year: 2023,
+            month: 8,
+            day,
+        }
+    }
+
+    fn attack(stars: usize, order: usize) -> WarAttack {
+        WarAttack {
+            destruction: 100,
+            stars,
+            duration: 60,
+            order: Some(order),
+        }
+    }
+
+    fn storage() -> ClanStorage {
+        let mut storage = ClanStorage::default();
+        for tag in ["#A", "#B", "#C", "#D"] {
+            storage
+                .player_names
+                .insert(PlayerTag(tag.to_string()), tag.to_string());
+        }
+
+        storage.wars.insert(
+            time(2),
+            WarStats {
+                start_time: time(2),
+                end_time: None,
+                finished: true,
+                summary: None,
+                summary_only: false,
+                members: [
+                    ("#A", vec![attack(3, 4)]),
+                    ("#B", vec![attack(3, 2)]),
+                    ("#C", vec![attack(2, 1), attack(1, 3)]),
+                ]
+                .into_iter()
+                .map(|(tag, attacks)| (PlayerTag(tag.to_string()), MemberWarStats { attacks }))
+                .collect(),
+            },
+        );
+
+        storage.raid_weekend.insert(
+            time(4),
+            RaidWeekendStats {
+                start_time: time(4),
+                members: [(PlayerTag("#D".to_string()), RaidMember { looted: 5000 })]
+                    .into_iter()
+                    .collect::<HashMap<_, _>>(),
+            },
+        );
+
+        storage
+    }
+
+    #[test]
+    fn ranking() {
+        let scoring = ScoringConfig::default();
+        let leaderboard = storage().leaderboard(&scoring);
+
+        let order: Vec<_> = leaderboard
+            .iter()
+            .map(|p| (p.rank, p.tag.0.as_str()))
+            .collect();
+
+        // A, B and C all have the same Score and War-Stars, but C attacked first and B attacked
+        // before A. D has a lower Score as 5000 Loot is worth less than 3 War-Stars
+        assert_eq!(vec![(1, "#C"), (2, "#B"), (3, "#A"), (4, "#D")], order);
+        assert_eq!(Some((time(2), 1)), leaderboard[0].first_attack);
+        assert_eq!(None, leaderboard[3].first_attack);
+    }
+
+    #[test]
+    fn ranking_by_player_tag() {
+        let mut storage = ClanStorage::default();
+        for tag in ["#C", "#A", "#B"] {
+            storage
+                .player_names
+                .insert(PlayerTag(tag.to_string()), tag.to_string());
+        }
+
+        let order: Vec<_> = storage
+            .leaderboard(&ScoringConfig::default())
+            .into_iter()
+            .map(|p| p.tag.0)
+            .collect();
+
+        assert_eq!(vec!["#A", "#B", "#C"], order);
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{ClanTag, PlayerLinks, PlayerTag};
+
+/// The maximum length of a single Reminder-Message, leaves some room below Discords Limit
+const MAX_MESSAGE_LENGTH: usize = 1900;
+
+/// A War that is currently running
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenWar {
+    pub clan: ClanTag,
+    pub kind: WarKind,
+    pub end_time: DateTime<Utc>,
+    /// The Members that still have unused Attacks
+    pub missing: Vec<MissingAttacks>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarKind {
+    Regular,
+    /// The 0-based Round of the Clan War League
+    Cwl {
+        round: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingAttacks {
+    pub tag: PlayerTag,
+    pub name: String,
+    pub remaining: usize,
+}
+
+/// Keeps track of the Wars for which a Reminder was already sent, so every War is only
+/// reminded once
+#[derive(Debug, Default)]
+pub struct Reminders {
+    sent: HashSet<(ClanTag, WarKind, DateTime<Utc>)>,
+}
+
+impl Reminders {
+    /// Returns the Wars that end within `before_end` of `now`, still have Members with unused
+    /// Attacks and have not been reminded yet. The returned Wars are considered reminded.
+    pub fn due(
+        &mut self,
+        wars: Vec<OpenWar>,
+        now: DateTime<Utc>,
+        before_end: Duration,
+    ) -> Vec<OpenWar> {
+        self.sent.retain(|(_, _, end)| *end > now);
+
+        wars.into_iter()
+            .filter(|war| !war.missing.is_empty())
+            .filter(|war| war.end_time > now && war.end_time - before_end <= now)
+            .filter(|war| self.sent.insert((war.clan.clone(), war.kind, war.end_time)))
+            .collect()
+    }
+}
+
+impl OpenWar {
+    /// Builds the Reminder-Messages for the War, Members linked to a Discord-User are mentioned
+    pub fn reminder_messages(&self, links: &PlayerLinks, now: DateTime<Utc>) -> Vec<String> {
+        let war = match self.kind {
+            WarKind::Regular => "War".to_string(),
+            WarKind::Cwl { round } => format!("CWL Round {}", round + 1),
+        };
+        let minutes = (self.end_time - now).num_minutes().max(0);
+
+        let header = format!(
+            "The {} of {} ends in {} minutes, the following Players still have Attacks left:",
+            war, self.clan.0, minutes
+        );
+
+        let lines = self.missing.iter().map(|member| {
+            let mention = links
+                .user(&member.tag)
+                .map(|user| format!("<@{}> ", user))
+                .unwrap_or_default();
+
+            format!(
+                "{}{} ({}): {} Attack{}",
+                mention,
+                member.name,
+                member.tag.0,
+                member.remaining,
+                if member.remaining == 1 { "" } else { "s" }
+            )
+        });
+
+        let mut messages = vec![header];
+        for line in lines {
+            let current = messages.last_mut().unwrap();
+            if current.len() + line.len() + 1 > MAX_MESSAGE_LENGTH {
+                messages.push(line);
+            } else {
+                current.push('\n');
+                current.push_str(&line);
+            }
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 8, 4, hour, minute, 0).unwrap()
+    }
+
+    fn war(missing: usize) -> OpenWar {
+        OpenWar {
+            clan: ClanTag("#CLAN".to_string()),
+            kind: WarKind::Regular,
+            end_time: time(12, 0),
+            missing: (0..missing)
+                .map(|i| MissingAttacks {
+                    tag: PlayerTag(format!("#P{}", i)),
+                    name: format!("Player {}", i),
+                    remaining: 2,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn due_once() {
+        let mut reminders = Reminders::default();
+        let before = Duration::hours(1);
+
+        assert!(reminders.due(vec![war(1)], time(10, 0), before).is_empty());
+        assert_eq!(1, reminders.due(vec![war(1)], time(11, 5), before).len());
+        assert!(reminders.due(vec![war(1)], time(11, 10), before).is_empty());
+        // Wars without missing Attacks or that already ended are not reminded
+        assert!(reminders.due(vec![war(0)], time(11, 10), before).is_empty());
+        assert!(reminders.due(vec![war(1)], time(12, 5), before).is_empty());
+    }
+
+    #[test]
+    fn mentions_linked() {
+        let mut links = PlayerLinks::default();
+        links.link(1234, PlayerTag("#P0".to_string()));
+
+        let messages = war(2).reminder_messages(&links, time(11, 30));
+        assert_eq!(
+            vec!["The War of #CLAN ends in 30 minutes, the following Players still have Attacks left:\n<@1234> Player 0 (#P0): 2 Attacks\nPlayer 1 (#P1): 2 Attacks".to_string()],
+            messages
+        );
+    }
+
+    #[test]
+    fn split_messages() {
+        let messages = war(100).reminder_messages(&PlayerLinks::default(), time(11, 30));
+
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|m| m.len() <= MAX_MESSAGE_LENGTH));
+    }
+}
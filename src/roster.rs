@@ -0,0 +1,234 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{ClanStorage, PlayerTag};
+
+/// The Role of a Member in the Clan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    Member,
+    /// Called Elder in the Game
+    Admin,
+    CoLeader,
+    Leader,
+    #[serde(other)]
+    Unknown,
+}
+
+/// The Members of a Clan and how they changed during a Season
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Roster {
+    /// The Members at the last Update with their Role
+    pub members: HashMap<PlayerTag, Role>,
+    /// The Changes in the Season, oldest first
+    pub events: Vec<RosterEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RosterEvent {
+    pub time: DateTime<Utc>,
+    pub player: PlayerTag,
+    pub change: RosterChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RosterChange {
+    Joined {
+        role: Role,
+    },
+    /// The Player left or was kicked, with the Role they had before
+    Left {
+        role: Role,
+    },
+    RoleChanged {
+        from: Role,
+        to: Role,
+    },
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::CoLeader => "coLeader",
+            Self::Leader => "leader",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "member" => Self::Member,
+            "admin" => Self::Admin,
+            "coLeader" => Self::CoLeader,
+            "leader" => Self::Leader,
+            _ => Self::Unknown,
+        })
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Member => "Member",
+            Self::Admin => "Elder",
+            Self::CoLeader => "Co-Leader",
+            Self::Leader => "Leader",
+            Self::Unknown => "Unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Roster {
+    /// Records the Changes compared to the last Update. If no Members are known yet, the Members
+    /// are only recorded without any Changes, as it is unknown when they joined.
+    pub fn update(&mut self, members: HashMap<PlayerTag, Role>, now: DateTime<Utc>) {
+        if self.members.is_empty() {
+            self.members = members;
+            return;
+        }
+
+        let mut changes: Vec<(PlayerTag, RosterChange)> = Vec::new();
+        for (player, role) in self.members.iter() {
+            match members.get(player) {
+                None => changes.push((player.clone(), RosterChange::Left { role: *role })),
+                Some(current) if current != role => changes.push((
+                    player.clone(),
+                    RosterChange::RoleChanged {
+                        from: *role,
+                        to: *current,
+                    },
+                )),
+                Some(_) => {}
+            }
+        }
+        for (player, role) in members.iter() {
+            if !self.members.contains_key(player) {
+                changes.push((player.clone(), RosterChange::Joined { role: *role }));
+            }
+        }
+
+        // Changes detected in the same Update are ordered by Player to be deterministic
+        changes.sort_by(|(first, _), (second, _)| first.0.cmp(&second.0));
+        self.events
+            .extend(changes.into_iter().map(|(player, change)| RosterEvent {
+                time: now,
+                player,
+                change,
+            }));
+        self.members = members;
+    }
+}
+
+impl ClanStorage {
+    /// Whether the Player was part of the Clan in the Season, but has left since. Without any
+    /// Roster information every known Player is treated as a current Member.
+    pub fn is_former_member(&self, tag: &PlayerTag) -> bool {
+        !self.roster.members.is_empty() && !self.roster.members.contains_key(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn tag(raw: &str) -> PlayerTag {
+        PlayerTag(raw.to_string())
+    }
+
+    #[test]
+    fn roster_changes() {
+        let first = Utc.with_ymd_and_hms(2023, 8, 1, 12, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2023, 8, 2, 12, 0, 0).unwrap();
+
+        let mut roster = Roster::default();
+        roster.update(
+            [(tag("#AB"), Role::Leader), (tag("#CD"), Role::Member)]
+                .into_iter()
+                .collect(),
+            first,
+        );
+        assert!(roster.events.is_empty());
+
+        roster.update(
+            [
+                (tag("#AB"), Role::Leader),
+                (tag("#EF"), Role::Member),
+                (tag("#CD"), Role::Admin),
+            ]
+            .into_iter()
+            .collect(),
+            first,
+        );
+        roster.update(
+            [(tag("#AB"), Role::Leader), (tag("#EF"), Role::Member)]
+                .into_iter()
+                .collect(),
+            second,
+        );
+
+        assert_eq!(
+            vec![
+                RosterEvent {
+                    time: first,
+                    player: tag("#CD"),
+                    change: RosterChange::RoleChanged {
+                        from: Role::Member,
+                        to: Role::Admin
+                    },
+                },
+                RosterEvent {
+                    time: first,
+                    player: tag("#EF"),
+                    change: RosterChange::Joined { role: Role::Member },
+                },
+                RosterEvent {
+                    time: second,
+                    player: tag("#CD"),
+                    change: RosterChange::Left { role: Role::Admin },
+                },
+            ],
+            roster.events
+        );
+
+        let mut storage = ClanStorage {
+            roster,
+            ..Default::default()
+        };
+        assert!(storage.is_former_member(&tag("#CD")));
+        assert!(!storage.is_former_member(&tag("#EF")));
+
+        storage.roster = Roster::default();
+        assert!(!storage.is_former_member(&tag("#CD")));
+    }
+
+    #[test]
+    fn parse_roles() {
+        let roles: Vec<Role> =
+            serde_json::from_str(r#"["member", "admin", "coLeader", "leader", "notMember"]"#)
+                .unwrap();
+        assert_eq!(
+            vec![
+                Role::Member,
+                Role::Admin,
+                Role::CoLeader,
+                Role::Leader,
+                Role::Unknown
+            ],
+            roles
+        );
+        assert_eq!(Ok(Role::CoLeader), Role::CoLeader.as_str().parse());
+    }
+}
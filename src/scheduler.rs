@@ -0,0 +1,217 @@
+use std::{fmt::Debug, future::Future, time::Duration};
+
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use serde::{Deserialize, Deserializer};
+
+/// The independent Collectors, every Collector runs on its own Schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Collector {
+    Names,
+    /// The regular Wars, including the reconciliation with the War-Log
+    War,
+    Cwl,
+    ClanGames,
+    Raids,
+}
+
+/// The Time in which a Collector is active, outside of it the Collector is not run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum ActiveWindow {
+    Always,
+    /// The Days of the Month, both inclusive
+    Days {
+        first: u32,
+        last: u32,
+    },
+    /// The Days of the Week, both inclusive, may wrap around the end of the Week
+    Weekdays {
+        #[serde(deserialize_with = "deserialize_weekday")]
+        first: Weekday,
+        #[serde(deserialize_with = "deserialize_weekday")]
+        last: Weekday,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// The Time between two Runs
+    pub interval: Duration,
+    /// The maximum Time between two Runs while the Collector keeps failing
+    pub max_backoff: Duration,
+    pub window: ActiveWindow,
+}
+
+fn deserialize_weekday<'de, D>(deserializer: D) -> Result<Weekday, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    raw.parse()
+        .map_err(|_| serde::de::Error::custom(format!("Invalid Weekday {:?}", raw)))
+}
+
+impl Collector {
+    pub const ALL: [Self; 5] = [
+        Self::Names,
+        Self::War,
+        Self::Cwl,
+        Self::ClanGames,
+        Self::Raids,
+    ];
+
+    /// The Schedule used if none is configured
+    pub fn default_schedule(&self) -> Schedule {
+        let (interval, window) = match self {
+            Self::Names => (600, ActiveWindow::Always),
+            Self::War => (90, ActiveWindow::Always),
+            // The League Groups are only available in the first days of the Month
+            Self::Cwl => (90, ActiveWindow::Days { first: 1, last: 10 }),
+            Self::ClanGames => (
+                600,
+                ActiveWindow::Days {
+                    first: 22,
+                    last: 28,
+                },
+            ),
+            // Raid Weekends run from Friday to Monday
+            Self::Raids => (
+                900,
+                ActiveWindow::Weekdays {
+                    first: Weekday::Fri,
+                    last: Weekday::Mon,
+                },
+            ),
+        };
+
+        Schedule {
+            interval: Duration::from_secs(interval),
+            max_backoff: Duration::from_secs(30 * 60),
+            window,
+        }
+    }
+}
+
+impl ActiveWindow {
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        match *self {
+            Self::Always => true,
+            Self::Days { first, last } => (first..=last).contains(&time.day()),
+            Self::Weekdays { first, last } => {
+                let day = time.weekday().num_days_from_monday();
+                let (first, last) = (first.num_days_from_monday(), last.num_days_from_monday());
+                if first <= last {
+                    (first..=last).contains(&day)
+                } else {
+                    day >= first || day <= last
+                }
+            }
+        }
+    }
+}
+
+impl Schedule {
+    /// The Time to wait before the next Run after the given Number of consecutive failed Runs,
+    /// doubles the Interval for every Failure up to the maximum Backoff
+    pub fn delay(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return self.interval;
+        }
+
+        let factor = 2u32.saturating_pow(failures.min(16));
+        self.interval
+            .saturating_mul(factor)
+            .min(self.max_backoff.max(self.interval))
+    }
+}
+
+/// Runs the Job according to the Schedule forever, backing off while it keeps failing
+pub async fn run_scheduled<F, Fut, E>(name: &str, schedule: Schedule, mut job: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: Debug,
+{
+    let mut failures = 0;
+    loop {
+        if schedule.window.contains(Utc::now()) {
+            match job().await {
+                Ok(()) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    tracing::warn!("{} failed {} times in a row: {:?}", name, failures, e);
+                }
+            }
+        } else {
+            tracing::trace!("{} is not active", name);
+            failures = 0;
+        }
+
+        tokio::time::sleep(schedule.delay(failures)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn windows() {
+        // 2023-08-04 is a Friday
+        let friday = Utc.with_ymd_and_hms(2023, 8, 4, 12, 0, 0).unwrap();
+        let wednesday = Utc.with_ymd_and_hms(2023, 8, 23, 12, 0, 0).unwrap();
+
+        assert!(ActiveWindow::Always.contains(friday));
+
+        let cwl = Collector::Cwl.default_schedule().window;
+        assert!(cwl.contains(friday));
+        assert!(!cwl.contains(wednesday));
+
+        let games = Collector::ClanGames.default_schedule().window;
+        assert!(!games.contains(friday));
+        assert!(games.contains(wednesday));
+
+        let raids = Collector::Raids.default_schedule().window;
+        assert!(raids.contains(friday));
+        assert!(raids.contains(Utc.with_ymd_and_hms(2023, 8, 7, 0, 0, 0).unwrap()));
+        assert!(!raids.contains(wednesday));
+    }
+
+    #[test]
+    fn backoff() {
+        let schedule = Schedule {
+            interval: Duration::from_secs(90),
+            max_backoff: Duration::from_secs(600),
+            window: ActiveWindow::Always,
+        };
+
+        assert_eq!(Duration::from_secs(90), schedule.delay(0));
+        assert_eq!(Duration::from_secs(180), schedule.delay(1));
+        assert_eq!(Duration::from_secs(360), schedule.delay(2));
+        assert_eq!(Duration::from_secs(600), schedule.delay(3));
+        assert_eq!(Duration::from_secs(600), schedule.delay(100));
+    }
+
+    #[test]
+    fn parse_window() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            window: ActiveWindow,
+        }
+
+        let parsed: Wrapper =
+            toml::from_str(r#"window = { weekdays = { first = "sat", last = "sun" } }"#).unwrap();
+        assert_eq!(
+            ActiveWindow::Weekdays {
+                first: Weekday::Sat,
+                last: Weekday::Sun
+            },
+            parsed.window
+        );
+
+        let parsed: Wrapper = toml::from_str(r#"window = "always""#).unwrap();
+        assert_eq!(ActiveWindow::Always, parsed.window);
+    }
+}
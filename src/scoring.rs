@@ -0,0 +1,195 @@
+use serde::Deserialize;
+
+use crate::PlayerSummary;
+
+/// The Model used to calculate the Gold-Pass Score of a Player based on their [`PlayerSummary`]
+///
+/// Every category is scored as `min(value / target, cap) * weight * 100`, so by default reaching
+/// the target of a category results in a score of 100 for that category.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    /// The Stars gained during CWL
+    pub cwl: CategoryScoring,
+    /// The Stars gained in regular Wars
+    pub war: CategoryScoring,
+    /// The Capital Gold looted during Raid Weekends
+    pub raid: CategoryScoring,
+    /// The Points earned in the Clan Games
+    pub games: CategoryScoring,
+    /// The Troops donated, not scored by default
+    pub donations: CategoryScoring,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryScoring {
+    /// The value needed to achieve the full score for this category
+    pub target: f64,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// The maximum ratio of `value / target` that counts towards the score, allows for limiting
+    /// how much overachieving in a single category can make up for others
+    #[serde(default)]
+    pub cap: Option<f64>,
+}
+
+/// The Score of a single Player, broken down by category
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Score {
+    pub cwl: f64,
+    pub war: f64,
+    pub raid: f64,
+    pub games: f64,
+    pub donations: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            cwl: CategoryScoring::new(21.0),
+            war: CategoryScoring::new(66.0),
+            raid: CategoryScoring::new(120000.0),
+            games: CategoryScoring::new(5000.0),
+            donations: CategoryScoring {
+                target: 1000.0,
+                weight: 0.0,
+                cap: None,
+            },
+        }
+    }
+}
+
+impl CategoryScoring {
+    pub fn new(target: f64) -> Self {
+        Self {
+            target,
+            weight: default_weight(),
+            cap: None,
+        }
+    }
+
+    pub fn score(&self, value: usize) -> f64 {
+        if self.target <= 0.0 {
+            return 0.0;
+        }
+
+        let ratio = value as f64 / self.target;
+        let ratio = match self.cap {
+            Some(cap) => ratio.min(cap),
+            None => ratio,
+        };
+
+        ratio * self.weight * 100.0
+    }
+}
+
+impl ScoringConfig {
+    pub fn score(&self, summary: &PlayerSummary) -> Score {
+        Score {
+            cwl: self.cwl.score(summary.cwl_stars),
+            war: self.war.score(summary.war_stars),
+            raid: self.raid.score(summary.raid_loot),
+            games: self.games.score(summary.games_score),
+            donations: self.donations.score(summary.donations),
+        }
+    }
+}
+
+impl Score {
+    pub fn total(&self) -> f64 {
+        self.cwl + self.war + self.raid + self.games + self.donations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> PlayerSummary {
+        PlayerSummary {
+            cwl_stars: 21,
+            war_stars: 33,
+            raid_loot: 240000,
+            games_score: 0,
+            donations: 1500,
+            donations_received: 200,
+        }
+    }
+
+    #[test]
+    fn default_scoring() {
+        let score = ScoringConfig::default().score(&summary());
+
+        assert_eq!(
+            Score {
+                cwl: 100.0,
+                war: 50.0,
+                raid: 200.0,
+                games: 0.0,
+                donations: 0.0,
+            },
+            score
+        );
+        assert_eq!(350.0, score.total());
+    }
+
+    #[test]
+    fn weights_and_caps() {
+        let scoring = ScoringConfig {
+            cwl: CategoryScoring {
+                target: 21.0,
+                weight: 2.0,
+                cap: None,
+            },
+            raid: CategoryScoring {
+                target: 120000.0,
+                weight: 1.0,
+                cap: Some(1.0),
+            },
+            ..Default::default()
+        };
+
+        let score = scoring.score(&summary());
+        assert_eq!(200.0, score.cwl);
+        assert_eq!(100.0, score.raid);
+
+        let scoring = ScoringConfig {
+            donations: CategoryScoring::new(1000.0),
+            ..Default::default()
+        };
+        assert_eq!(150.0, scoring.score(&summary()).donations);
+    }
+
+    #[test]
+    fn zero_target() {
+        assert_eq!(0.0, CategoryScoring::new(0.0).score(10));
+    }
+
+    #[test]
+    fn deserialize() {
+        let scoring: ScoringConfig = toml::from_str(
+            r#"
+[war]
+target = 60
+weight = 0.5
+
+[raid]
+target = 100000
+cap = 1.5
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(21.0, scoring.cwl.target);
+        assert_eq!(60.0, scoring.war.target);
+        assert_eq!(0.5, scoring.war.weight);
+        assert_eq!(None, scoring.war.cap);
+        assert_eq!(1.0, scoring.raid.weight);
+        assert_eq!(Some(1.5), scoring.raid.cap);
+    }
+}
@@ -7,23 +7,48 @@ use std::{
 use chrono::Datelike;
 use serde::{Deserialize, Serialize};
 
-use crate::{ClanTag, ClanWarLeagueSeason, PlayerTag, Time};
+use crate::{ClanTag, ClanWarLeagueSeason, EventPhase, PlayerLinks, PlayerTag, Roster, Time};
 
 mod files;
 pub use files::FileStorage;
 
 mod s3;
-pub use s3::S3Storage;
+pub use s3::{S3Storage, SnapshotPolicy};
 
 mod replicated;
-pub use replicated::Replicated;
+pub use replicated::{Replicated, WritePolicy};
+
+mod sqlite;
+pub use sqlite::SqliteStorage;
+
+mod migration;
+pub use migration::CURRENT_VERSION;
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// There is no stored Data yet, which is expected on the first start
+    NotFound,
+    Io(std::io::Error),
+    S3(::s3::error::S3Error),
+    /// The S3 Request returned a non successful Status-Code
+    S3Status(u16),
+    Sqlite(rusqlite::Error),
+    Serialize(serde_json::Error),
+    Deserialize(serde_json::Error),
+    /// The stored Data was written by a newer version of the Bot
+    UnsupportedVersion(u64),
+    /// The stored Data could not be migrated to the current version
+    Migration(&'static str),
+}
 
 pub trait StorageBackend: Send {
     fn write(
         &mut self,
         content: Vec<u8>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), ()>> + Send + 'static>>;
-    fn load(&mut self) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, ()>> + Send + 'static>>;
+    ) -> Pin<Box<dyn Future<Output = Result<(), StorageError>> + Send + 'static>>;
+    fn load(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>>;
 }
 
 impl<S> StorageBackend for Box<S>
@@ -33,11 +58,13 @@ where
     fn write(
         &mut self,
         content: Vec<u8>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), ()>> + Send + 'static>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), StorageError>> + Send + 'static>> {
         S::write(self.as_mut(), content)
     }
 
-    fn load(&mut self) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, ()>> + Send + 'static>> {
+    fn load(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>> {
         S::load(self.as_mut())
     }
 }
@@ -45,6 +72,8 @@ where
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Storage {
     clans: HashMap<ClanTag, HashMap<Season, ClanStorage>>,
+    #[serde(default)]
+    links: PlayerLinks,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -60,10 +89,22 @@ impl<'de> Deserialize<'de> for Season {
     {
         let raw = String::deserialize(deserializer)?;
 
-        let (raw_year, raw_month) = raw.split_once('-').ok_or(serde::de::Error::custom(""))?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::str::FromStr for Season {
+    type Err = &'static str;
 
-        let year = raw_year.parse().map_err(|e| serde::de::Error::custom(e))?;
-        let month = raw_month.parse().map_err(|e| serde::de::Error::custom(e))?;
+    /// Parses a Season in the format `YYYY-MM`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (raw_year, raw_month) = s.trim().split_once('-').ok_or("Expected YYYY-MM")?;
+
+        let year = raw_year.parse().map_err(|_| "Invalid Year")?;
+        let month = raw_month.parse().map_err(|_| "Invalid Month")?;
+        if !(1..=12).contains(&month) {
+            return Err("Invalid Month");
+        }
 
         Ok(Self { year, month })
     }
@@ -124,13 +165,43 @@ pub struct ClanStorage {
     pub wars: BTreeMap<Time, WarStats>,
     pub games: HashMap<PlayerTag, PlayerGamesStats>,
     pub raid_weekend: BTreeMap<Time, RaidWeekendStats>,
+    /// The Names of all the Players that were Members during the Season, including the ones that
+    /// left since
     pub player_names: HashMap<PlayerTag, String>,
+    #[serde(default)]
+    pub roster: Roster,
+    #[serde(default)]
+    pub donations: HashMap<PlayerTag, PlayerDonationStats>,
 }
 
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+/// The Clan Games of a Player, based on the total Points of the "Games Champion" Achievement
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct PlayerGamesStats {
+    /// The Total at the Start of the Event
     pub start_score: Option<usize>,
+    /// The Total at the End of the Event, capped to the maximum Points of the Event
     pub end_score: usize,
+    /// The Player was first seen after the Event started, so the Points earned before are not
+    /// counted
+    #[serde(default)]
+    pub late: bool,
+    /// The Baseline is the first Total seen in the Month, as recorded by older versions, instead
+    /// of the Total at the Start of the Event
+    #[serde(default)]
+    pub monthly_baseline: bool,
+}
+
+/// The Donations of a Player in a Season, tracked as the Deltas of the in-game Counters
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PlayerDonationStats {
+    /// The Troops donated in the Season
+    pub donated: usize,
+    /// The Troops received in the Season
+    pub received: usize,
+    /// The last seen value of the in-game `donations` Counter
+    pub last_donations: usize,
+    /// The last seen value of the in-game `donationsReceived` Counter
+    pub last_received: usize,
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -146,7 +217,29 @@ pub struct CwlWarStats {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WarStats {
     pub start_time: Time,
+    #[serde(default)]
+    pub end_time: Option<Time>,
+    /// Whether the War has ended and the stored State is final
+    #[serde(default)]
+    pub finished: bool,
     pub members: HashMap<PlayerTag, MemberWarStats>,
+    /// The Outcome of the War as reported by the War-Log
+    #[serde(default)]
+    pub summary: Option<WarSummary>,
+    /// The War was backfilled from the War-Log, which does not contain the Attacks of the
+    /// Members, so only the Summary is known
+    #[serde(default)]
+    pub summary_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WarSummary {
+    /// `win`, `lose` or `tie`
+    pub result: Option<String>,
+    pub team_size: usize,
+    pub stars: usize,
+    pub destruction: f32,
+    pub opponent: Option<ClanTag>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -159,6 +252,9 @@ pub struct WarAttack {
     pub destruction: usize,
     pub stars: usize,
     pub duration: usize,
+    /// The Position of the Attack in the overall order of Attacks in the War
+    #[serde(default)]
+    pub order: Option<usize>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -172,10 +268,77 @@ pub struct RaidMember {
     pub looted: usize,
 }
 
+impl PlayerGamesStats {
+    /// Starts tracking a Player with the given Total
+    pub fn new(total: usize, phase: EventPhase) -> Self {
+        Self {
+            start_score: Some(total),
+            end_score: total,
+            late: phase != EventPhase::Before,
+            monthly_baseline: false,
+        }
+    }
+
+    /// Records the current Total of the Player. Before the Event the Baseline follows the Total,
+    /// so it is the last Total seen before the Event started. After the Event nothing changes.
+    pub fn update(&mut self, total: usize, phase: EventPhase, max_points: usize) {
+        match phase {
+            EventPhase::Before => *self = Self::new(total, phase),
+            EventPhase::Running => {
+                let start = *self.start_score.get_or_insert(total);
+                self.end_score = total.clamp(start, start + max_points);
+            }
+            EventPhase::Ended => {}
+        }
+    }
+
+    /// The Points earned by the Player in the Clan Games
+    pub fn score(&self) -> usize {
+        self.end_score
+            .saturating_sub(self.start_score.unwrap_or(self.end_score))
+    }
+}
+
+impl PlayerDonationStats {
+    /// Starts tracking from the given Counters, everything donated before is not counted
+    pub fn new(donations: usize, received: usize) -> Self {
+        Self {
+            donated: 0,
+            received: 0,
+            last_donations: donations,
+            last_received: received,
+        }
+    }
+
+    /// Continues tracking from the last seen Counters of a previous Season
+    pub fn continue_from(previous: &Self) -> Self {
+        Self::new(previous.last_donations, previous.last_received)
+    }
+
+    /// Adds the Changes since the last seen Counters, the in-game Counters are reset at the end
+    /// of every in-game Season, which is detected by a Counter decreasing
+    pub fn update(&mut self, donations: usize, received: usize) {
+        self.donated += counter_delta(self.last_donations, donations);
+        self.received += counter_delta(self.last_received, received);
+        self.last_donations = donations;
+        self.last_received = received;
+    }
+}
+
+fn counter_delta(last: usize, current: usize) -> usize {
+    if current >= last {
+        current - last
+    } else {
+        // The Counter was reset and everything since then has been added again
+        current
+    }
+}
+
 impl Storage {
     pub fn empty() -> Self {
         Self {
             clans: HashMap::new(),
+            links: PlayerLinks::default(),
         }
     }
 
@@ -201,88 +364,199 @@ impl Storage {
         self.clans.get(tag).and_then(|s| s.get(season))
     }
 
-    pub async fn load(store: &mut dyn StorageBackend) -> Result<Self, ()> {
-        let content = store.load().await.map_err(|e| ())?;
-        serde_json::from_slice(&content).map_err(|e| ())
+    /// All the Seasons stored for the Clan
+    pub fn clan_seasons_mut(
+        &mut self,
+        tag: &ClanTag,
+    ) -> impl Iterator<Item = (&Season, &mut ClanStorage)> + '_ {
+        self.clans
+            .get_mut(tag)
+            .into_iter()
+            .flat_map(|s| s.iter_mut())
+    }
+
+    pub fn links(&self) -> &PlayerLinks {
+        &self.links
+    }
+
+    pub fn set_links(&mut self, links: PlayerLinks) {
+        self.links = links;
     }
 
-    pub async fn save(&self, store: &mut dyn StorageBackend) -> Result<(), ()> {
-        let content = serde_json::to_vec(&self).map_err(|e| {
-            tracing::error!("Serializing {:?}", e);
-            ()
-        })?;
+    /// Loads the Storage from the Backend, returns [`StorageError::NotFound`] if nothing has
+    /// been stored yet. Data stored by older versions is migrated to the current version.
+    pub async fn load(store: &mut dyn StorageBackend) -> Result<Self, StorageError> {
+        let content = store.load().await?;
+        let document = serde_json::from_slice(&content).map_err(StorageError::Deserialize)?;
+        serde_json::from_value(migration::upgrade(document)?).map_err(StorageError::Deserialize)
+    }
 
-        store.write(content).await.map_err(|e| {
-            tracing::error!("Storing {:?}", e);
-            ()
+    pub async fn save(&self, store: &mut dyn StorageBackend) -> Result<(), StorageError> {
+        let content = serde_json::to_vec(&migration::Envelope {
+            version: CURRENT_VERSION,
+            saved_at: Some(chrono::Utc::now().timestamp_millis()),
+            data: self,
         })
+        .map_err(StorageError::Serialize)?;
+
+        store.write(content).await
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlayerSummary {
     pub cwl_stars: usize,
     pub war_stars: usize,
     pub raid_loot: usize,
     pub games_score: usize,
+    pub donations: usize,
+    pub donations_received: usize,
 }
 
 impl ClanStorage {
-    pub fn players_summary(&self) -> impl Iterator<Item = (PlayerTag, PlayerSummary)> + '_ {
+    /// The Summaries of the current Members, the Members that left during the Season are only
+    /// included if `include_former` is set
+    pub fn players_summary(
+        &self,
+        include_former: bool,
+    ) -> impl Iterator<Item = (PlayerTag, PlayerSummary)> + '_ {
         // TODO
         // Get all the players we have some data for
-        let players: HashSet<PlayerTag> = self.player_names.keys().cloned().collect();
+        let players: HashSet<PlayerTag> = self
+            .player_names
+            .keys()
+            .filter(|tag| include_former || !self.is_former_member(tag))
+            .cloned()
+            .collect();
 
         players.into_iter().map(|ptag| {
-            let cwl_stars: usize = self
-                .cwl
-                .wars
-                .iter()
-                .map(|war| {
-                    war.members
-                        .get(&ptag)
-                        .map(|mstats| mstats.attacks.iter().map(|a| a.stars).sum::<usize>())
-                        .unwrap_or(0)
-                })
-                .sum();
-
-            let war_stars: usize = self
-                .wars
-                .values()
-                .map(|war| {
-                    war.members
-                        .get(&ptag)
-                        .map(|mstats| mstats.attacks.iter().map(|att| att.stars).sum::<usize>())
-                        .unwrap_or(0)
-                })
-                .sum();
-
-            let raid_loot: usize = self
-                .raid_weekend
-                .values()
-                .map(|raid| {
-                    raid.members
-                        .get(&ptag)
-                        .map(|rstats| rstats.looted)
-                        .unwrap_or(0)
-                })
-                .sum();
-
-            let games_score = self
-                .games
-                .get(&ptag)
-                .map(|s| s.end_score - s.start_score.unwrap_or(s.end_score))
-                .unwrap_or(0);
-
-            (
-                ptag,
-                PlayerSummary {
-                    cwl_stars,
-                    war_stars,
-                    raid_loot,
-                    games_score,
-                },
-            )
+            let summary = self.player_summary(&ptag);
+            (ptag, summary)
         })
     }
+
+    pub fn player_summary(&self, ptag: &PlayerTag) -> PlayerSummary {
+        let cwl_stars: usize = self
+            .cwl
+            .wars
+            .iter()
+            .map(|war| {
+                war.members
+                    .get(ptag)
+                    .map(|mstats| mstats.attacks.iter().map(|a| a.stars).sum::<usize>())
+                    .unwrap_or(0)
+            })
+            .sum();
+
+        let war_stars: usize = self
+            .wars
+            .values()
+            .map(|war| {
+                war.members
+                    .get(ptag)
+                    .map(|mstats| mstats.attacks.iter().map(|att| att.stars).sum::<usize>())
+                    .unwrap_or(0)
+            })
+            .sum();
+
+        let raid_loot: usize = self
+            .raid_weekend
+            .values()
+            .map(|raid| {
+                raid.members
+                    .get(ptag)
+                    .map(|rstats| rstats.looted)
+                    .unwrap_or(0)
+            })
+            .sum();
+
+        let games_score = self.games.get(ptag).map(|s| s.score()).unwrap_or(0);
+
+        let donations = self.donations.get(ptag);
+
+        PlayerSummary {
+            cwl_stars,
+            war_stars,
+            raid_loot,
+            games_score,
+            donations: donations.map(|d| d.donated).unwrap_or(0),
+            donations_received: donations.map(|d| d.received).unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_season() {
+        assert_eq!(
+            Ok(Season {
+                year: 2023,
+                month: 8
+            }),
+            "2023-08".parse()
+        );
+        assert_eq!(
+            Ok(Season {
+                year: 2023,
+                month: 12
+            }),
+            "2023-12".parse()
+        );
+        assert!("2023".parse::<Season>().is_err());
+        assert!("2023-13".parse::<Season>().is_err());
+        assert!("2023-00".parse::<Season>().is_err());
+        assert!("08-2023".parse::<Season>().is_err());
+    }
+
+    #[test]
+    fn season_serde_roundtrip() {
+        let season = Season {
+            year: 2023,
+            month: 8,
+        };
+
+        let serialized = serde_json::to_string(&season).unwrap();
+        assert_eq!("\"2023-08\"", serialized);
+        assert_eq!(season, serde_json::from_str(&serialized).unwrap());
+    }
+
+    #[test]
+    fn games_event() {
+        let mut stats = PlayerGamesStats::new(10000, EventPhase::Before);
+        stats.update(10500, EventPhase::Before, 4000);
+        assert_eq!(Some(10500), stats.start_score);
+
+        stats.update(12000, EventPhase::Running, 4000);
+        assert_eq!(1500, stats.score());
+        // Points above the Maximum of the Event are not counted
+        stats.update(15000, EventPhase::Running, 4000);
+        assert_eq!(4000, stats.score());
+        stats.update(20000, EventPhase::Ended, 4000);
+        assert_eq!(4000, stats.score());
+        assert!(!stats.late);
+
+        let late = PlayerGamesStats::new(3000, EventPhase::Running);
+        assert!(late.late);
+        assert_eq!(0, late.score());
+    }
+
+    #[test]
+    fn donation_deltas() {
+        let mut stats = PlayerDonationStats::new(100, 40);
+        stats.update(150, 40);
+        stats.update(180, 70);
+        assert_eq!((80, 30), (stats.donated, stats.received));
+
+        // The in-game Season ended and the Counters were reset
+        stats.update(20, 5);
+        assert_eq!((100, 35), (stats.donated, stats.received));
+
+        // A new Season continues where the previous one stopped
+        let mut next = PlayerDonationStats::continue_from(&stats);
+        next.update(25, 5);
+        assert_eq!((5, 0), (next.donated, next.received));
+    }
 }
@@ -1,9 +1,19 @@
-use std::{path::PathBuf, pin::Pin};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
 
-use crate::StorageBackend;
+use crate::{StorageBackend, StorageError};
 
+/// Stores the Data in a single File.
+///
+/// Writes are atomic, the content is first written to a temporary File which then replaces the
+/// previous File. The previous Files are kept as rotated Backups (`data.json.1`, `data.json.2`,
+/// ...) and the newest valid one is used when the File itself can not be loaded.
 pub struct FileStorage {
     path: PathBuf,
+    backups: usize,
 }
 
 impl FileStorage {
@@ -11,7 +21,16 @@ impl FileStorage {
     where
         P: Into<PathBuf>,
     {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            backups: 3,
+        }
+    }
+
+    /// Sets the Number of Backups to keep
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
     }
 
     pub async fn read(&mut self) -> Result<Vec<u8>, std::io::Error> {
@@ -19,12 +38,99 @@ impl FileStorage {
     }
 
     pub async fn write(&mut self, content: &[u8]) -> Result<(), std::io::Error> {
-        if !self.path.exists() {
-            tokio::fs::File::create(&self.path).await?;
+        let path = self.path.clone();
+        let backups = self.backups;
+        let content = content.to_vec();
+
+        tokio::task::spawn_blocking(move || write_atomic(&path, backups, &content))
+            .await
+            .expect("Writing File panicked")
+    }
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Writes the content to a temporary File, syncs it to disk, rotates the Backups and finally
+/// replaces the File with the temporary one. The current File is kept in place until it is
+/// replaced, so there is always a File at the Path.
+fn write_atomic(path: &Path, backups: usize, content: &[u8]) -> Result<(), std::io::Error> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut tmp = std::fs::File::create(&tmp_path)?;
+    tmp.write_all(content)?;
+    tmp.sync_all()?;
+    drop(tmp);
+
+    if backups > 0 && path.exists() {
+        for index in (1..backups).rev() {
+            let from = backup_path(path, index);
+            if from.exists() {
+                std::fs::rename(&from, backup_path(path, index + 1))?;
+            }
+        }
+        let backup = backup_path(path, 1);
+        // Only left over if a single Backup is kept
+        if backup.exists() {
+            std::fs::remove_file(&backup)?;
         }
+        if let Err(e) = std::fs::hard_link(path, &backup) {
+            tracing::debug!("Hard-Linking Backup failed, copying instead: {:?}", e);
+            std::fs::copy(path, &backup)?;
+        }
+    }
+
+    std::fs::rename(&tmp_path, path)?;
+
+    // Make sure the renames are persisted as well
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    std::fs::File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Loads the content of the File, falls back to the newest Backup containing valid JSON if the
+/// File itself is missing or invalid
+fn load_with_backups(path: &Path, backups: usize) -> Result<Vec<u8>, StorageError> {
+    let mut first_error = None;
 
-        tokio::fs::write(&self.path, content).await
+    for (index, candidate) in core::iter::once(path.to_path_buf())
+        .chain((1..=backups).map(|i| backup_path(path, i)))
+        .enumerate()
+    {
+        let content = match std::fs::read(&candidate) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                tracing::error!("Reading {:?}: {:?}", candidate, e);
+                first_error.get_or_insert(StorageError::Io(e));
+                continue;
+            }
+        };
+
+        match serde_json::from_slice::<serde::de::IgnoredAny>(&content) {
+            Ok(_) => {
+                if index > 0 {
+                    tracing::warn!("Falling back to Backup {:?}", candidate);
+                }
+                return Ok(content);
+            }
+            Err(e) => {
+                tracing::error!("Invalid content in {:?}: {:?}", candidate, e);
+                first_error.get_or_insert(StorageError::Deserialize(e));
+            }
+        }
     }
+
+    Err(first_error.unwrap_or(StorageError::NotFound))
 }
 
 impl StorageBackend for FileStorage {
@@ -32,31 +138,115 @@ impl StorageBackend for FileStorage {
     fn write(
         &mut self,
         content: Vec<u8>,
-    ) -> Pin<Box<dyn std::future::Future<Output = Result<(), ()>> + Send + 'static>> {
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<(), StorageError>> + Send + 'static>> {
         let path = self.path.clone();
+        let backups = self.backups;
 
         Box::pin(async move {
             tracing::trace!("Storing to File");
 
-            if !path.exists() {
-                tokio::fs::File::create(&path).await.map_err(|e| {
-                    tracing::error!("Creating file {:?}", e);
-                    ()
-                })?;
-            }
-
-            tokio::fs::write(&path, &content).await.map_err(|e| {
-                tracing::error!("Writing File {:?}", e);
-                ()
-            })
+            tokio::task::spawn_blocking(move || write_atomic(&path, backups, &content))
+                .await
+                .expect("Writing File panicked")
+                .map_err(StorageError::Io)
         })
     }
 
     fn load(
         &mut self,
-    ) -> Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, ()>> + Send + 'static>> {
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>>
+    {
         let path = self.path.clone();
+        let backups = self.backups;
+
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || load_with_backups(&path, backups))
+                .await
+                .expect("Loading File panicked")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Storage;
+
+    #[tokio::test]
+    async fn load_errors() {
+        let dir = std::env::temp_dir().join(format!("gold-pass-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut missing = FileStorage::new(dir.join("missing.json"));
+        assert!(matches!(
+            Storage::load(&mut missing).await,
+            Err(StorageError::NotFound)
+        ));
+
+        let corrupt_path = dir.join("corrupt.json");
+        std::fs::write(&corrupt_path, b"{ \"clans\": ").unwrap();
+        let mut corrupt = FileStorage::new(corrupt_path);
+        assert!(matches!(
+            Storage::load(&mut corrupt).await,
+            Err(StorageError::Deserialize(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotate_backups() {
+        let dir = std::env::temp_dir().join(format!("gold-pass-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.json");
+
+        let mut storage = FileStorage::new(&path).with_backups(2);
+        for i in 0..4 {
+            StorageBackend::write(&mut storage, format!("[{}]", i).into_bytes())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!("[3]", std::fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "[2]",
+            std::fs::read_to_string(dir.join("data.json.1")).unwrap()
+        );
+        assert_eq!(
+            "[1]",
+            std::fs::read_to_string(dir.join("data.json.2")).unwrap()
+        );
+        assert!(!dir.join("data.json.3").exists());
+        assert!(!dir.join("data.json.tmp").exists());
+
+        // A truncated File falls back to the newest valid Backup
+        std::fs::write(&path, b"[3").unwrap();
+        std::fs::write(dir.join("data.json.1"), b"").unwrap();
+        assert_eq!(b"[1]".to_vec(), storage.load().await.unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn single_backup() {
+        let dir = std::env::temp_dir().join(format!("gold-pass-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.json");
+
+        let mut storage = FileStorage::new(&path).with_backups(1);
+        for i in 0..3 {
+            StorageBackend::write(&mut storage, format!("[{}]", i).into_bytes())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!("[2]", std::fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "[1]",
+            std::fs::read_to_string(dir.join("data.json.1")).unwrap()
+        );
+        assert!(!dir.join("data.json.2").exists());
 
-        Box::pin(async move { tokio::fs::read(&path).await.map_err(|e| ()) })
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,305 @@
+//! The Migrations for the persisted Storage.
+//!
+//! The Storage is persisted in a versioned Envelope `{ "version": <n>, "data": <storage> }`.
+//! Documents written before the Envelope was introduced are the bare Storage and are treated as
+//! version 0. Every Migration upgrades a Document by exactly one version, so older Documents are
+//! upgraded by applying all the following Migrations in order.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::StorageError;
+
+/// The Version of the Storage written by this version of the Bot
+pub const CURRENT_VERSION: u64 = 2;
+
+/// The Migrations, the Migration at index `n` upgrades a Document from version `n` to `n + 1`
+const MIGRATIONS: [fn(Value) -> Result<Value, StorageError>; CURRENT_VERSION as usize] =
+    [v0_to_v1, v1_to_v2];
+
+#[derive(Debug, Serialize)]
+pub struct Envelope<T> {
+    pub version: u64,
+    /// When the Data was saved, as Milliseconds since the Unix-Epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<i64>,
+    pub data: T,
+}
+
+/// When the Document was saved, `None` for Documents without the Time in their Envelope
+pub fn saved_at(document: &Value) -> Option<i64> {
+    match document {
+        Value::Object(obj) if obj.contains_key("version") && obj.contains_key("data") => {
+            obj.get("saved_at").and_then(|s| s.as_i64())
+        }
+        _ => None,
+    }
+}
+
+/// Unwraps the Document and upgrades it to the current version
+pub fn upgrade(document: Value) -> Result<Value, StorageError> {
+    let (version, mut data) = match document {
+        Value::Object(mut obj) if obj.contains_key("version") && obj.contains_key("data") => {
+            let version = obj
+                .get("version")
+                .and_then(|v| v.as_u64())
+                .ok_or(StorageError::Migration("Invalid version"))?;
+            (version, obj.remove("data").unwrap())
+        }
+        other => (0, other),
+    };
+
+    if version > CURRENT_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        tracing::info!("Migrating Storage from version {} to {}", from, from + 1);
+        data = migration(data)?;
+    }
+
+    Ok(data)
+}
+
+/// Adds the Links between Discord-Users and Players, the order of War-Attacks and the End-Time
+/// and State of the regular Wars
+fn v0_to_v1(mut data: Value) -> Result<Value, StorageError> {
+    let storage = data
+        .as_object_mut()
+        .ok_or(StorageError::Migration("Storage is not an Object"))?;
+
+    storage
+        .entry("links")
+        .or_insert_with(|| serde_json::json!({ "players": {} }));
+
+    let clans = storage
+        .get_mut("clans")
+        .and_then(|c| c.as_object_mut())
+        .ok_or(StorageError::Migration("Missing Clans"))?;
+
+    for season in clans
+        .values_mut()
+        .filter_map(|seasons| seasons.as_object_mut())
+        .flat_map(|seasons| seasons.values_mut())
+    {
+        let cwl_wars = season
+            .pointer_mut("/cwl/wars")
+            .and_then(|w| w.as_array_mut())
+            .into_iter()
+            .flatten();
+        for war in cwl_wars {
+            add_attack_order(war);
+        }
+
+        let wars = season
+            .get_mut("wars")
+            .and_then(|w| w.as_object_mut())
+            .into_iter()
+            .flat_map(|w| w.values_mut());
+        for war in wars {
+            if let Some(war) = war.as_object_mut() {
+                war.entry("end_time").or_insert(Value::Null);
+                war.entry("finished").or_insert(Value::Bool(false));
+            }
+            add_attack_order(war);
+        }
+    }
+
+    Ok(data)
+}
+
+/// Adds the Roster, the Donations and the Summaries of the regular Wars. The Clan Games were
+/// tracked from the first Total seen in the Month, which is marked as such as it is not the
+/// Total at the Start of the Event
+fn v1_to_v2(mut data: Value) -> Result<Value, StorageError> {
+    let clans = data
+        .get_mut("clans")
+        .and_then(|c| c.as_object_mut())
+        .ok_or(StorageError::Migration("Missing Clans"))?;
+
+    for season in clans
+        .values_mut()
+        .filter_map(|seasons| seasons.as_object_mut())
+        .flat_map(|seasons| seasons.values_mut())
+        .filter_map(|season| season.as_object_mut())
+    {
+        season
+            .entry("roster")
+            .or_insert_with(|| serde_json::json!({ "members": {}, "events": [] }));
+        season
+            .entry("donations")
+            .or_insert_with(|| serde_json::json!({}));
+
+        let wars = season
+            .get_mut("wars")
+            .and_then(|w| w.as_object_mut())
+            .into_iter()
+            .flat_map(|w| w.values_mut())
+            .filter_map(|w| w.as_object_mut());
+        for war in wars {
+            war.entry("summary").or_insert(Value::Null);
+            war.entry("summary_only").or_insert(Value::Bool(false));
+        }
+
+        let games = season
+            .get_mut("games")
+            .and_then(|g| g.as_object_mut())
+            .into_iter()
+            .flat_map(|g| g.values_mut())
+            .filter_map(|g| g.as_object_mut());
+        for player in games {
+            player.entry("late").or_insert(Value::Bool(false));
+            player.insert("monthly_baseline".to_string(), Value::Bool(true));
+        }
+    }
+
+    Ok(data)
+}
+
+fn add_attack_order(war: &mut Value) {
+    let attacks = war
+        .get_mut("members")
+        .and_then(|m| m.as_object_mut())
+        .into_iter()
+        .flat_map(|m| m.values_mut())
+        .filter_map(|m| m.get_mut("attacks"))
+        .filter_map(|a| a.as_array_mut())
+        .flatten()
+        .filter_map(|a| a.as_object_mut());
+
+    for attack in attacks {
+        attack.entry("order").or_insert(Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClanTag, PlayerTag, Role, Season, Storage};
+
+    const V0: &str = include_str!("../../fixtures/storage/v0.json");
+    const V1: &str = include_str!("../../fixtures/storage/v1.json");
+    const V2: &str = include_str!("../../fixtures/storage/v2.json");
+
+    fn load(content: &str) -> Storage {
+        let document = serde_json::from_str(content).unwrap();
+        serde_json::from_value(upgrade(document).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrate_v0() {
+        let storage = load(V0);
+
+        let clan = storage
+            .get(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap();
+
+        let war = clan.wars.values().next().unwrap();
+        assert!(!war.finished);
+        assert_eq!(None, war.end_time);
+
+        let player = PlayerTag("#P2Y8QJ0L".to_string());
+        assert_eq!(2, war.members[&player].attacks.len());
+        assert!(war.members[&player]
+            .attacks
+            .iter()
+            .all(|a| a.order.is_none()));
+        assert_eq!(5, clan.player_summary(&player).war_stars);
+        assert_eq!(None, storage.links().user(&player));
+    }
+
+    #[test]
+    fn migrate_v1() {
+        let storage = load(V1);
+
+        let player = PlayerTag("#P2Y8QJ0L".to_string());
+        assert_eq!(Some(123456789), storage.links().user(&player));
+
+        let clan = storage
+            .get(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap();
+        let war = clan.wars.values().next().unwrap();
+        assert!(war.finished);
+        assert_eq!(Some(1), war.members[&player].attacks[0].order);
+        assert_eq!(None, war.summary);
+        assert!(!war.summary_only);
+
+        // The Clan Games were tracked from the first Total seen in the Month
+        assert!(clan.games.values().all(|g| g.monthly_baseline && !g.late));
+        assert_eq!(4000, clan.games[&player].score());
+        assert!(clan.roster.members.is_empty());
+        assert!(clan.donations.is_empty());
+    }
+
+    #[test]
+    fn current_version() {
+        let storage = load(V2);
+
+        let player = PlayerTag("#P2Y8QJ0L".to_string());
+        assert_eq!(Some(123456789), storage.links().user(&player));
+
+        let clan = storage
+            .get(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap();
+        assert_eq!(2, clan.wars.len());
+        assert_eq!(1, clan.wars.values().filter(|w| w.summary_only).count());
+        assert!(clan.games.values().all(|g| !g.monthly_baseline));
+        assert!(clan.games[&player].late);
+        assert_eq!(Some(&Role::Leader), clan.roster.members.get(&player));
+        assert_eq!(1, clan.roster.events.len());
+        assert_eq!(205, clan.donations[&player].donated);
+
+        let document = serde_json::from_str(V2).unwrap();
+        assert_eq!(Some(1692086400000), saved_at(&document));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let storage = load(V0);
+
+        let content = serde_json::to_string(&Envelope {
+            version: CURRENT_VERSION,
+            saved_at: Some(1692086400000),
+            data: &storage,
+        })
+        .unwrap();
+        let reloaded = load(&content);
+
+        let document = serde_json::from_str(&content).unwrap();
+        assert_eq!(Some(1692086400000), saved_at(&document));
+        assert_eq!(None, saved_at(&serde_json::from_str(V0).unwrap()));
+
+        assert_eq!(
+            serde_json::to_value(&storage).unwrap(),
+            serde_json::to_value(&reloaded).unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let document = serde_json::json!({ "version": CURRENT_VERSION + 1, "data": {} });
+
+        assert!(matches!(
+            upgrade(document),
+            Err(StorageError::UnsupportedVersion(_))
+        ));
+    }
+}
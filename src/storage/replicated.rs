@@ -1,13 +1,143 @@
-use crate::StorageBackend;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
+use serde::Deserialize;
+
+use super::migration;
+use crate::{StorageBackend, StorageError};
+
+static DIVERGENCES: once_cell::sync::Lazy<prometheus::IntCounterVec> =
+    once_cell::sync::Lazy::new(|| {
+        let counter = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "storage_replica_divergences",
+                "The Number of times a Replica diverged from the chosen Source on load",
+            ),
+            &["replica"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(counter.clone())).unwrap();
+        counter
+    });
+
+static REPAIRS: once_cell::sync::Lazy<prometheus::IntCounterVec> =
+    once_cell::sync::Lazy::new(|| {
+        let counter = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "storage_replica_repairs",
+                "The Number of times a stale Replica was re-synced from the chosen Source",
+            ),
+            &["replica"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(counter.clone())).unwrap();
+        counter
+    });
+
+static WRITE_FAILURES: once_cell::sync::Lazy<prometheus::IntCounterVec> =
+    once_cell::sync::Lazy::new(|| {
+        let counter = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "storage_replica_write_failures",
+                "The Number of failed writes to a Replica",
+            ),
+            &["replica"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(counter.clone())).unwrap();
+        counter
+    });
+
+/// How many Replicas need to be written successfully for a write to succeed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WritePolicy {
+    All,
+    Quorum,
+    #[default]
+    Any,
+}
+
+impl WritePolicy {
+    /// The Number of successful writes required for the given Number of Replicas
+    pub fn required(&self, replicas: usize) -> usize {
+        match self {
+            Self::All => replicas,
+            Self::Quorum => replicas / 2 + 1,
+            Self::Any => 1.min(replicas),
+        }
+    }
+}
+
+impl FromStr for WritePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "quorum" => Ok(Self::Quorum),
+            "any" => Ok(Self::Any),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Replicates the Data across multiple Backends.
+///
+/// On load the Data that was saved most recently is used as the Source, if multiple Replicas
+/// were saved at the same time the Data most Replicas agree on is used. All the other Backends
+/// that are missing the Data or contain different Data are re-synced from it, Backends that
+/// failed to load for any other reason are left untouched.
 pub struct Replicated {
-    primary: Box<dyn StorageBackend>,
-    secondary: Box<dyn StorageBackend>,
+    backends: Arc<Vec<Mutex<Box<dyn StorageBackend>>>>,
+    policy: WritePolicy,
 }
 
 impl Replicated {
-    pub fn new(primary: Box<dyn StorageBackend>, secondary: Box<dyn StorageBackend>) -> Self {
-        Self { primary, secondary }
+    pub fn new(backends: Vec<Box<dyn StorageBackend>>, policy: WritePolicy) -> Self {
+        Self {
+            backends: Arc::new(backends.into_iter().map(Mutex::new).collect()),
+            policy,
+        }
+    }
+}
+
+/// The Data loaded from a single Replica
+struct Loaded {
+    content: Vec<u8>,
+    /// The parsed Content without the Time it was saved at
+    document: Option<serde_json::Value>,
+    saved_at: Option<i64>,
+}
+
+impl Loaded {
+    fn new(content: Vec<u8>) -> Self {
+        let mut document = serde_json::from_slice::<serde_json::Value>(&content).ok();
+        let saved_at = document.as_ref().and_then(migration::saved_at);
+        if let Some(serde_json::Value::Object(obj)) = document.as_mut() {
+            obj.remove("saved_at");
+        }
+
+        Self {
+            content,
+            document,
+            saved_at,
+        }
+    }
+
+    /// Compares the Content of two Replicas, JSON Content is compared independent of the order
+    /// of the entries, as Backends like SQLite do not preserve it, and of the Time it was saved
+    fn same_content(&self, other: &Self) -> bool {
+        if self.content == other.content {
+            return true;
+        }
+
+        match (&self.document, &other.document) {
+            (Some(f), Some(s)) => f == s,
+            _ => false,
+        }
     }
 }
 
@@ -16,33 +146,321 @@ impl StorageBackend for Replicated {
     fn write(
         &mut self,
         content: Vec<u8>,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ()>> + Send + 'static>> {
-        let pfut = self.primary.write(content.clone());
-        let sfut = self.secondary.write(content);
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), StorageError>> + Send + 'static>,
+    > {
+        let futures: Vec<_> = self
+            .backends
+            .iter()
+            .map(|b| b.lock().unwrap().write(content.clone()))
+            .collect();
+        let required = self.policy.required(futures.len());
 
         Box::pin(async move {
             tracing::trace!("Storing Replicated");
 
-            let pres = pfut.await;
-            let sres = sfut.await;
+            let mut succeeded = 0;
+            let mut first_error = None;
+            for (index, fut) in futures.into_iter().enumerate() {
+                match fut.await {
+                    Ok(()) => succeeded += 1,
+                    Err(e) => {
+                        tracing::error!("Writing Replica {}: {:?}", index, e);
+                        WRITE_FAILURES
+                            .with_label_values(&[&index.to_string()])
+                            .inc();
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
 
-            pres.ok().or(sres.ok()).ok_or(())
+            match first_error {
+                Some(e) if succeeded < required => Err(e),
+                _ => Ok(()),
+            }
         })
     }
 
     fn load(
         &mut self,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, ()>> + Send + 'static>>
-    {
-        let pfut = self.primary.load();
-        let sfut = self.secondary.load();
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>,
+    > {
+        let futures: Vec<_> = self
+            .backends
+            .iter()
+            .map(|b| b.lock().unwrap().load())
+            .collect();
+        let backends = self.backends.clone();
 
         Box::pin(async move {
-            if let Ok(r) = pfut.await {
-                return Ok(r);
+            let mut results = Vec::with_capacity(futures.len());
+            for fut in futures {
+                results.push(fut.await.map(Loaded::new));
             }
 
-            sfut.await
+            // The most recently saved Data wins, ties are broken by the Number of Replicas
+            // agreeing on it and then by the configured order
+            let source = results
+                .iter()
+                .enumerate()
+                .rev()
+                .filter_map(|(index, r)| r.as_ref().ok().map(|l| (index, l)))
+                .max_by_key(|(_, loaded)| {
+                    let agreeing = results
+                        .iter()
+                        .filter_map(|r| r.as_ref().ok())
+                        .filter(|other| loaded.same_content(other))
+                        .count();
+                    (loaded.saved_at, agreeing)
+                })
+                .map(|(index, _)| index);
+            let source = match source {
+                Some(s) => s,
+                None => {
+                    // Only report NotFound if none of the Backends failed for another reason
+                    return Err(results
+                        .into_iter()
+                        .filter_map(|r| r.err())
+                        .find(|e| !matches!(e, StorageError::NotFound))
+                        .unwrap_or(StorageError::NotFound));
+                }
+            };
+            let loaded = results[source].as_ref().unwrap();
+            let content = loaded.content.clone();
+
+            for (index, result) in results.iter().enumerate() {
+                match result {
+                    Ok(l) if loaded.same_content(l) => continue,
+                    Ok(_) => {
+                        tracing::warn!("Replica {} diverged from Replica {}", index, source);
+                    }
+                    Err(StorageError::NotFound) => {
+                        tracing::warn!("Replica {} is missing the Data", index);
+                    }
+                    Err(e) => {
+                        // The Data might still be recoverable, so it is not overwritten
+                        tracing::error!(
+                            "Loading Replica {} failed, not re-syncing: {:?}",
+                            index,
+                            e
+                        );
+                        continue;
+                    }
+                };
+
+                DIVERGENCES.with_label_values(&[&index.to_string()]).inc();
+
+                let repair = backends[index].lock().unwrap().write(content.clone());
+                match repair.await {
+                    Ok(()) => {
+                        tracing::info!("Re-synced Replica {} from Replica {}", index, source);
+                        REPAIRS.with_label_values(&[&index.to_string()]).inc();
+                    }
+                    Err(e) => {
+                        tracing::error!("Re-syncing Replica {}: {:?}", index, e);
+                    }
+                }
+            }
+
+            Ok(content)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Backend keeping the Data in Memory that can be shared with the Test
+    #[derive(Clone, Default)]
+    struct Memory {
+        content: Arc<Mutex<Option<Vec<u8>>>>,
+        failing: Arc<std::sync::atomic::AtomicBool>,
+        /// Only loading fails, as for Data that can not be decoded
+        corrupt: bool,
+    }
+
+    impl Memory {
+        fn with(content: &str) -> Self {
+            let memory = Self::default();
+            *memory.content.lock().unwrap() = Some(content.as_bytes().to_vec());
+            memory
+        }
+
+        fn corrupt(content: &str) -> Self {
+            Self {
+                corrupt: true,
+                ..Self::with(content)
+            }
+        }
+
+        fn failing() -> Self {
+            let memory = Self::default();
+            memory
+                .failing
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+            memory
+        }
+
+        fn get(&self) -> Option<String> {
+            self.content
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|c| String::from_utf8(c.clone()).unwrap())
+        }
+    }
+
+    impl StorageBackend for Memory {
+        fn write(
+            &mut self,
+            content: Vec<u8>,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = Result<(), StorageError>> + Send + 'static>,
+        > {
+            let this = self.clone();
+            Box::pin(async move {
+                if this.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                    return Err(StorageError::S3Status(500));
+                }
+                *this.content.lock().unwrap() = Some(content);
+                Ok(())
+            })
+        }
+
+        fn load(
+            &mut self,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>,
+        > {
+            let this = self.clone();
+            Box::pin(async move {
+                if this.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                    return Err(StorageError::S3Status(500));
+                }
+                if this.corrupt {
+                    return Err(StorageError::Migration("Corrupt"));
+                }
+                this.content
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .ok_or(StorageError::NotFound)
+            })
+        }
+    }
+
+    fn replicated(backends: &[Memory], policy: WritePolicy) -> Replicated {
+        Replicated::new(
+            backends
+                .iter()
+                .map(|b| Box::new(b.clone()) as Box<dyn StorageBackend>)
+                .collect(),
+            policy,
+        )
+    }
+
+    #[test]
+    fn required_writes() {
+        assert_eq!(3, WritePolicy::All.required(3));
+        assert_eq!(2, WritePolicy::Quorum.required(3));
+        assert_eq!(3, WritePolicy::Quorum.required(4));
+        assert_eq!(1, WritePolicy::Any.required(3));
+    }
+
+    #[tokio::test]
+    async fn write_policy() {
+        let backends = [Memory::default(), Memory::default(), Memory::failing()];
+
+        assert!(replicated(&backends, WritePolicy::Any)
+            .write(b"1".to_vec())
+            .await
+            .is_ok());
+        assert!(replicated(&backends, WritePolicy::Quorum)
+            .write(b"2".to_vec())
+            .await
+            .is_ok());
+        assert!(replicated(&backends, WritePolicy::All)
+            .write(b"3".to_vec())
+            .await
+            .is_err());
+
+        // The write still reached the healthy Replicas
+        assert_eq!(Some("3".to_string()), backends[0].get());
+    }
+
+    #[tokio::test]
+    async fn repair_on_load() {
+        let backends = [
+            Memory::with(r#"{"a": 1, "b": 2}"#),
+            Memory::with(r#"{"b": 2, "a": 1}"#),
+            Memory::with(r#"{"a": 0}"#),
+            Memory::default(),
+        ];
+
+        let content = replicated(&backends, WritePolicy::Any)
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(br#"{"a": 1, "b": 2}"#.to_vec(), content);
+
+        // Replicas with the same Data in a different order are not rewritten
+        assert_eq!(Some(r#"{"b": 2, "a": 1}"#.to_string()), backends[1].get());
+        assert_eq!(Some(r#"{"a": 1, "b": 2}"#.to_string()), backends[2].get());
+        assert_eq!(Some(r#"{"a": 1, "b": 2}"#.to_string()), backends[3].get());
+    }
+
+    #[tokio::test]
+    async fn load_newest() {
+        let stale = r#"{"version": 1, "saved_at": 1000, "data": {"a": 0}}"#;
+        let newer = r#"{"version": 1, "saved_at": 2000, "data": {"a": 1}}"#;
+        let backends = [
+            Memory::with(stale),
+            Memory::with(newer),
+            Memory::with(stale),
+            Memory::corrupt(stale),
+        ];
+
+        let content = replicated(&backends, WritePolicy::Any)
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(newer.as_bytes().to_vec(), content);
+
+        assert_eq!(Some(newer.to_string()), backends[0].get());
+        assert_eq!(Some(newer.to_string()), backends[2].get());
+        // Replicas that failed to load are not overwritten
+        assert_eq!(Some(stale.to_string()), backends[3].get());
+    }
+
+    #[tokio::test]
+    async fn load_same_data() {
+        // Replicas only differing in the Time of the Save are not rewritten
+        let first = r#"{"version": 1, "saved_at": 1000, "data": {"a": 1}}"#;
+        let second = r#"{"version": 1, "saved_at": 2000, "data": {"a": 1}}"#;
+        let backends = [Memory::with(first), Memory::with(second)];
+
+        let content = replicated(&backends, WritePolicy::Any)
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(second.as_bytes().to_vec(), content);
+        assert_eq!(Some(first.to_string()), backends[0].get());
+    }
+
+    #[tokio::test]
+    async fn load_errors() {
+        let backends = [Memory::default(), Memory::failing()];
+        assert!(matches!(
+            replicated(&backends, WritePolicy::Any).load().await,
+            Err(StorageError::S3Status(500))
+        ));
+
+        let backends = [Memory::default(), Memory::default()];
+        assert!(matches!(
+            replicated(&backends, WritePolicy::Any).load().await,
+            Err(StorageError::NotFound)
+        ));
+    }
+}
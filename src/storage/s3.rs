@@ -1,17 +1,189 @@
-use crate::StorageBackend;
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+use crate::{StorageBackend, StorageError};
+
+/// The Format of the Timestamp used as the Name of a Snapshot, sorts chronologically
+const SNAPSHOT_FORMAT: &str = "%Y-%m-%dT%H";
 
 pub struct S3Storage {
     bucket: s3::Bucket,
+    prefix: String,
     filename: String,
+    snapshots: Option<SnapshotPolicy>,
+    state: Arc<Mutex<S3State>>,
+}
+
+/// Controls how often immutable Snapshots of the Data are stored and how many are kept
+#[derive(Debug, Clone)]
+pub struct SnapshotPolicy {
+    pub interval: chrono::Duration,
+    /// The Number of Snapshots to keep, older ones are deleted
+    pub keep: usize,
+}
+
+#[derive(Debug, Default)]
+struct S3State {
+    /// The Hash of the Content currently stored in the Bucket
+    stored_hash: Option<Vec<u8>>,
+    /// The Name of the newest Snapshot, `None` if it has not been determined yet
+    last_snapshot: Option<String>,
 }
 
 impl S3Storage {
     pub fn new(bucket: s3::Bucket) -> Self {
         Self {
             bucket,
+            prefix: String::new(),
             filename: "storage.json".to_string(),
+            snapshots: None,
+            state: Arc::new(Mutex::new(S3State::default())),
+        }
+    }
+
+    /// Stores all Objects below the given Prefix, allows multiple Instances to share a Bucket
+    pub fn with_prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// The Key of the Object containing the Data, relative to the Prefix
+    pub fn with_key<S>(mut self, key: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.filename = key.into();
+        self
+    }
+
+    pub fn with_snapshots(mut self, policy: SnapshotPolicy) -> Self {
+        self.snapshots = Some(policy);
+        self
+    }
+}
+
+/// Joins the Prefix and the Key of an Object
+fn object_path(prefix: &str, key: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}/{}", prefix, key)
+    }
+}
+
+fn snapshot_name(time: chrono::DateTime<chrono::Utc>) -> String {
+    format!("{}.json", time.format(SNAPSHOT_FORMAT))
+}
+
+/// The Snapshots that should be deleted to only keep the newest `keep` ones
+fn expired_snapshots(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let expired = names.len().saturating_sub(keep);
+    names.truncate(expired);
+    names
+}
+
+/// The Hash of the Content without the Time it was saved at, as that changes on every save even
+/// if the Data is the same
+fn content_hash(content: &[u8]) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(content) {
+        Ok(serde_json::Value::Object(mut document)) if document.contains_key("saved_at") => {
+            document.remove("saved_at");
+            Sha256::digest(serde_json::to_vec(&document).unwrap()).to_vec()
+        }
+        _ => Sha256::digest(content).to_vec(),
+    }
+}
+
+/// Stores a Snapshot of the Content if the last one is older than the configured Interval and
+/// removes the Snapshots exceeding the Retention
+async fn snapshot(
+    bucket: &s3::Bucket,
+    prefix: &str,
+    policy: &SnapshotPolicy,
+    state: &Mutex<S3State>,
+    content: &[u8],
+) -> Result<(), StorageError> {
+    let snapshot_prefix = object_path(prefix, "snapshots/");
+    let now = chrono::Utc::now();
+
+    let last_snapshot = state.lock().unwrap().last_snapshot.clone();
+    let mut existing = None;
+    let last_snapshot = match last_snapshot {
+        Some(l) => Some(l),
+        None => {
+            let names = list_snapshots(bucket, &snapshot_prefix).await?;
+            let newest = names.iter().max().cloned();
+            existing = Some(names);
+            newest
+        }
+    };
+
+    // Snapshot Names sort chronologically, so they can be compared directly
+    if let Some(last) = last_snapshot.as_ref() {
+        if last.as_str() > snapshot_name(now - policy.interval).as_str() {
+            state.lock().unwrap().last_snapshot = last_snapshot;
+            return Ok(());
+        }
+    }
+
+    let name = snapshot_name(now);
+    tracing::info!("Storing Snapshot {:?}", name);
+    match bucket
+        .put_object_with_content_type(
+            format!("{}{}", snapshot_prefix, name),
+            content,
+            "application/json",
+        )
+        .await
+    {
+        Ok(r) if (200..300).contains(&r.status_code()) => {}
+        Ok(r) => return Err(StorageError::S3Status(r.status_code())),
+        Err(e) => return Err(StorageError::S3(e)),
+    };
+    state.lock().unwrap().last_snapshot = Some(name.clone());
+
+    let mut names = match existing {
+        Some(names) => names,
+        None => list_snapshots(bucket, &snapshot_prefix).await?,
+    };
+    if !names.contains(&name) {
+        names.push(name);
+    }
+
+    for expired in expired_snapshots(names, policy.keep) {
+        tracing::debug!("Deleting expired Snapshot {:?}", expired);
+        if let Err(e) = bucket
+            .delete_object(format!("{}{}", snapshot_prefix, expired))
+            .await
+        {
+            tracing::error!("Deleting Snapshot {:?}: {:?}", expired, e);
         }
     }
+
+    Ok(())
+}
+
+/// The Names of all the Snapshots below the Prefix
+async fn list_snapshots(
+    bucket: &s3::Bucket,
+    snapshot_prefix: &str,
+) -> Result<Vec<String>, StorageError> {
+    let results = bucket
+        .list(snapshot_prefix.to_string(), None)
+        .await
+        .map_err(StorageError::S3)?;
+
+    Ok(results
+        .into_iter()
+        .flat_map(|r| r.contents)
+        .filter_map(|o| o.key.strip_prefix(snapshot_prefix).map(|k| k.to_string()))
+        .collect())
 }
 
 impl StorageBackend for S3Storage {
@@ -19,9 +191,14 @@ impl StorageBackend for S3Storage {
     fn write(
         &mut self,
         content: Vec<u8>,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ()>> + Send + 'static>> {
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), StorageError>> + Send + 'static>,
+    > {
         let bucket = self.bucket.clone();
-        let filename = self.filename.clone();
+        let prefix = self.prefix.clone();
+        let filename = object_path(&self.prefix, &self.filename);
+        let snapshots = self.snapshots.clone();
+        let state = self.state.clone();
 
         Box::pin(async move {
             let filename = filename;
@@ -29,42 +206,169 @@ impl StorageBackend for S3Storage {
 
             tracing::trace!("Storing to S3 Bucket");
 
-            if let Ok(previous) = bucket.get_object(filename.clone()).await {
-                let previous_content = previous.to_vec();
-
-                if content == previous_content {
-                    tracing::trace!("Skipping upload as content is the same");
-                    return Ok(());
+            if let Some(policy) = snapshots.as_ref() {
+                if let Err(e) = snapshot(&bucket, &prefix, policy, &state, &content).await {
+                    tracing::error!("Storing Snapshot: {:?}", e);
                 }
             }
 
+            let hash = content_hash(&content);
+            if state.lock().unwrap().stored_hash.as_ref() == Some(&hash) {
+                tracing::trace!("Skipping upload as content is the same");
+                return Ok(());
+            }
+
             let res = bucket.put_object_with_content_type(&filename, &content, "application/json");
 
             match res.await {
-                Ok(_) => {}
-                Err(e) => {
-                    tracing::error!("{:?}", e);
-
-                    return Err(());
+                Ok(r) if (200..300).contains(&r.status_code()) => {
+                    state.lock().unwrap().stored_hash = Some(hash);
+                    Ok(())
                 }
-            };
-
-            Ok(())
+                Ok(r) => Err(StorageError::S3Status(r.status_code())),
+                Err(e) => Err(StorageError::S3(e)),
+            }
         })
     }
 
     fn load(
         &mut self,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, ()>> + Send + 'static>>
-    {
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>,
+    > {
         let bucket = self.bucket.clone();
-        let filename = self.filename.clone();
+        let filename = object_path(&self.prefix, &self.filename);
+        let state = self.state.clone();
 
         Box::pin(async move {
-            match bucket.get_object(filename).await {
-                Ok(c) => Ok(c.to_vec()),
-                Err(e) => Err(()),
-            }
+            let content = match bucket.get_object(filename).await {
+                Ok(c) if c.status_code() == 200 => c.to_vec(),
+                Ok(c) if c.status_code() == 404 => return Err(StorageError::NotFound),
+                Ok(c) => return Err(StorageError::S3Status(c.status_code())),
+                Err(s3::error::S3Error::Http(404, _)) => return Err(StorageError::NotFound),
+                Err(e) => return Err(StorageError::S3(e)),
+            };
+
+            state.lock().unwrap().stored_hash = Some(content_hash(&content));
+            Ok(content)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::Storage;
+
+    /// Starts a Server accepting every Request like a Bucket would, returns its Endpoint and the
+    /// Number of Uploads it received
+    fn bucket_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let uploads = Arc::new(AtomicUsize::new(0));
+
+        let counter = uploads.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                loop {
+                    let mut request_line = String::new();
+                    if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                        break;
+                    }
+
+                    let mut length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    if request_line.starts_with("PUT") {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                    reader
+                        .get_mut()
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nETag: \"etag\"\r\nContent-Length: 0\r\n\r\n",
+                        )
+                        .unwrap();
+                }
+            }
+        });
+
+        (endpoint, uploads)
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!("storage.json", object_path("", "storage.json"));
+        assert_eq!("clan-a/storage.json", object_path("clan-a", "storage.json"));
+        assert_eq!("clan-a/snapshots/", object_path("/clan-a/", "snapshots/"));
+    }
+
+    #[test]
+    fn snapshot_names() {
+        let time = chrono::Utc
+            .with_ymd_and_hms(2026, 10, 17, 0, 30, 0)
+            .unwrap();
+        assert_eq!("2026-10-17T00.json", snapshot_name(time));
+    }
+
+    #[tokio::test]
+    async fn skip_unchanged() {
+        let (endpoint, uploads) = bucket_server();
+        let bucket = s3::Bucket::new(
+            "gold-pass",
+            s3::Region::Custom {
+                region: "default".to_string(),
+                endpoint,
+            },
+            s3::creds::Credentials::new(Some("access"), Some("secret"), None, None, None).unwrap(),
+        )
+        .unwrap()
+        .with_path_style();
+        let mut backend = S3Storage::new(bucket);
+
+        let storage = Storage::empty();
+        storage.save(&mut backend).await.unwrap();
+        assert_eq!(1, uploads.load(Ordering::SeqCst));
+
+        // The Time of the Save changed, but the Data did not
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        storage.save(&mut backend).await.unwrap();
+        assert_eq!(1, uploads.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn retention() {
+        let names = vec![
+            "2026-10-17T00.json".to_string(),
+            "2026-10-15T00.json".to_string(),
+            "2026-10-16T00.json".to_string(),
+        ];
+
+        assert_eq!(
+            vec!["2026-10-15T00.json".to_string()],
+            expired_snapshots(names.clone(), 2)
+        );
+        assert!(expired_snapshots(names, 5).is_empty());
+    }
+}
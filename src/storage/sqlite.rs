@@ -0,0 +1,1086 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::{
+    ClanStorage, ClanTag, CwlWarStats, MemberWarStats, PlayerDonationStats, PlayerGamesStats,
+    PlayerTag, RaidMember, RaidWeekendStats, Role, RosterChange, RosterEvent, Season, Storage,
+    StorageBackend, StorageError, Time, WarAttack, WarStats, WarSummary,
+};
+
+use super::migration::{self, Envelope, CURRENT_VERSION};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS clans (
+    tag TEXT PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS seasons (
+    clan TEXT NOT NULL REFERENCES clans(tag),
+    season TEXT NOT NULL,
+    PRIMARY KEY (clan, season)
+);
+CREATE TABLE IF NOT EXISTS player_names (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    player TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (clan, season, player),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS roster_members (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    player TEXT NOT NULL,
+    role TEXT NOT NULL,
+    PRIMARY KEY (clan, season, player),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS roster_events (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    time TEXT NOT NULL,
+    player TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    role TEXT NOT NULL,
+    previous_role TEXT,
+    PRIMARY KEY (clan, season, position),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS cwl_rounds (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    PRIMARY KEY (clan, season, round),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS cwl_members (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    player TEXT NOT NULL,
+    PRIMARY KEY (clan, season, round, player),
+    FOREIGN KEY (clan, season, round) REFERENCES cwl_rounds(clan, season, round)
+);
+CREATE TABLE IF NOT EXISTS cwl_attacks (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    player TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    destruction INTEGER NOT NULL,
+    stars INTEGER NOT NULL,
+    duration INTEGER NOT NULL,
+    attack_order INTEGER,
+    PRIMARY KEY (clan, season, round, player, position),
+    FOREIGN KEY (clan, season, round, player) REFERENCES cwl_members(clan, season, round, player)
+);
+CREATE TABLE IF NOT EXISTS wars (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT,
+    finished INTEGER NOT NULL,
+    PRIMARY KEY (clan, season, start_time),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS war_members (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    player TEXT NOT NULL,
+    PRIMARY KEY (clan, season, start_time, player),
+    FOREIGN KEY (clan, season, start_time) REFERENCES wars(clan, season, start_time)
+);
+CREATE TABLE IF NOT EXISTS war_attacks (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    player TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    destruction INTEGER NOT NULL,
+    stars INTEGER NOT NULL,
+    duration INTEGER NOT NULL,
+    attack_order INTEGER,
+    PRIMARY KEY (clan, season, start_time, player, position),
+    FOREIGN KEY (clan, season, start_time, player)
+        REFERENCES war_members(clan, season, start_time, player)
+);
+CREATE TABLE IF NOT EXISTS war_summaries (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    summary_only INTEGER NOT NULL,
+    result TEXT,
+    team_size INTEGER NOT NULL,
+    stars INTEGER NOT NULL,
+    destruction REAL NOT NULL,
+    opponent TEXT,
+    PRIMARY KEY (clan, season, start_time),
+    FOREIGN KEY (clan, season, start_time) REFERENCES wars(clan, season, start_time)
+);
+CREATE TABLE IF NOT EXISTS raid_weekends (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    PRIMARY KEY (clan, season, start_time),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS raid_members (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    player TEXT NOT NULL,
+    looted INTEGER NOT NULL,
+    PRIMARY KEY (clan, season, start_time, player),
+    FOREIGN KEY (clan, season, start_time) REFERENCES raid_weekends(clan, season, start_time)
+);
+CREATE TABLE IF NOT EXISTS games (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    player TEXT NOT NULL,
+    start_score INTEGER,
+    end_score INTEGER NOT NULL,
+    late INTEGER NOT NULL DEFAULT 0,
+    monthly_baseline INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (clan, season, player),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS donations (
+    clan TEXT NOT NULL,
+    season TEXT NOT NULL,
+    player TEXT NOT NULL,
+    donated INTEGER NOT NULL,
+    received INTEGER NOT NULL,
+    last_donations INTEGER NOT NULL,
+    last_received INTEGER NOT NULL,
+    PRIMARY KEY (clan, season, player),
+    FOREIGN KEY (clan, season) REFERENCES seasons(clan, season)
+);
+CREATE TABLE IF NOT EXISTS links (
+    player TEXT PRIMARY KEY,
+    user INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+";
+
+/// The Tables containing the Data of a single Season, ordered so that rows are deleted before
+/// the rows they reference
+const SEASON_TABLES: [&str; 14] = [
+    "cwl_attacks",
+    "cwl_members",
+    "cwl_rounds",
+    "war_attacks",
+    "war_members",
+    "war_summaries",
+    "wars",
+    "raid_members",
+    "raid_weekends",
+    "games",
+    "donations",
+    "player_names",
+    "roster_members",
+    "roster_events",
+];
+
+/// Stores the Data in a SQLite Database with a Table for every kind of Stats.
+///
+/// Only the Seasons that changed since the last write are updated in the Database.
+pub struct SqliteStorage {
+    path: PathBuf,
+    state: Arc<Mutex<SqliteState>>,
+}
+
+#[derive(Default)]
+struct SqliteState {
+    connection: Option<Connection>,
+    /// The Seasons and Links as they were last written to the Database, stored as JSON-Values
+    /// as they can be compared independent of the order of the entries
+    written: HashMap<(ClanTag, Season), serde_json::Value>,
+    written_links: Option<serde_json::Value>,
+}
+
+impl SqliteStorage {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+            state: Arc::new(Mutex::new(SqliteState::default())),
+        }
+    }
+}
+
+impl SqliteState {
+    /// Opens the Database on first use and makes sure the Schema exists
+    fn connection(&mut self, path: &PathBuf) -> Result<&mut Connection, StorageError> {
+        if self.connection.is_none() {
+            let connection = Connection::open(path).map_err(StorageError::Sqlite)?;
+            connection
+                .execute_batch(SCHEMA)
+                .map_err(StorageError::Sqlite)?;
+            upgrade_schema(&connection).map_err(StorageError::Sqlite)?;
+            self.connection = Some(connection);
+        }
+
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    fn write(
+        &mut self,
+        path: &PathBuf,
+        storage: &Storage,
+        saved_at: Option<i64>,
+    ) -> Result<(), StorageError> {
+        let mut changed = Vec::new();
+        for (clan, seasons) in storage.clans.iter() {
+            for (season, stats) in seasons.iter() {
+                let serialized = serde_json::to_value(stats).map_err(StorageError::Serialize)?;
+                let key = (clan.clone(), season.clone());
+
+                if self.written.get(&key) != Some(&serialized) {
+                    changed.push((key, stats, serialized));
+                }
+            }
+        }
+        let links = serde_json::to_value(&storage.links).map_err(StorageError::Serialize)?;
+        let links_changed = self.written_links.as_ref() != Some(&links);
+
+        if changed.is_empty() && !links_changed {
+            tracing::trace!("Skipping write as nothing changed");
+            if let Some(saved_at) = saved_at {
+                write_saved_at(self.connection(path)?, saved_at).map_err(StorageError::Sqlite)?;
+            }
+            return Ok(());
+        }
+
+        let connection = self.connection(path)?;
+        let tx = connection.transaction().map_err(StorageError::Sqlite)?;
+
+        for clan in storage.clans.keys() {
+            tx.execute(
+                "INSERT OR IGNORE INTO clans (tag) VALUES (?1)",
+                params![clan.0],
+            )
+            .map_err(StorageError::Sqlite)?;
+        }
+
+        for ((clan, season), stats, _) in changed.iter() {
+            tracing::trace!("Writing Season {:?} of {:?}", season, clan);
+            write_season(&tx, clan, season, stats).map_err(StorageError::Sqlite)?;
+        }
+
+        if links_changed {
+            tx.execute("DELETE FROM links", [])
+                .map_err(StorageError::Sqlite)?;
+            for (player, user) in storage.links.iter() {
+                tx.execute(
+                    "INSERT INTO links (player, user) VALUES (?1, ?2)",
+                    params![player.0, user as i64],
+                )
+                .map_err(StorageError::Sqlite)?;
+            }
+        }
+
+        if let Some(saved_at) = saved_at {
+            write_saved_at(&tx, saved_at).map_err(StorageError::Sqlite)?;
+        }
+
+        tx.commit().map_err(StorageError::Sqlite)?;
+
+        for (key, _, serialized) in changed {
+            self.written.insert(key, serialized);
+        }
+        self.written_links = Some(links);
+
+        Ok(())
+    }
+
+    /// Loads the Storage and the Time it was saved at
+    fn load(&mut self, path: &PathBuf) -> Result<(Storage, Option<i64>), StorageError> {
+        if !path.exists() {
+            return Err(StorageError::NotFound);
+        }
+
+        let connection = self.connection(path)?;
+        let storage = read_storage(connection).map_err(StorageError::Sqlite)?;
+        let saved_at = read_saved_at(connection).map_err(StorageError::Sqlite)?;
+
+        if storage.clans.is_empty() {
+            return Err(StorageError::NotFound);
+        }
+
+        // The loaded Data is exactly what is stored in the Database
+        self.written.clear();
+        for (clan, seasons) in storage.clans.iter() {
+            for (season, stats) in seasons.iter() {
+                let serialized = serde_json::to_value(stats).map_err(StorageError::Serialize)?;
+                self.written
+                    .insert((clan.clone(), season.clone()), serialized);
+            }
+        }
+        self.written_links =
+            Some(serde_json::to_value(&storage.links).map_err(StorageError::Serialize)?);
+
+        Ok((storage, saved_at))
+    }
+}
+
+fn write_saved_at(connection: &Connection, saved_at: i64) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('saved_at', ?1)",
+        params![saved_at],
+    )?;
+    Ok(())
+}
+
+fn read_saved_at(connection: &Connection) -> rusqlite::Result<Option<i64>> {
+    let mut stmt = connection.prepare("SELECT value FROM meta WHERE key = 'saved_at'")?;
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Adds the Columns that were added to existing Tables after they were created
+fn upgrade_schema(connection: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = connection.prepare("SELECT name FROM pragma_table_info('games')")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    if !columns.iter().any(|c| c == "late") {
+        connection.execute(
+            "ALTER TABLE games ADD COLUMN late INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    if !columns.iter().any(|c| c == "monthly_baseline") {
+        connection.execute(
+            "ALTER TABLE games ADD COLUMN monthly_baseline INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+        // Tables without the late Column were written before the Baseline was taken at the
+        // Start of the Event
+        if !columns.iter().any(|c| c == "late") {
+            connection.execute("UPDATE games SET monthly_baseline = 1", [])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn season_key(season: &Season) -> String {
+    format!("{:04}-{:02}", season.year, season.month)
+}
+
+fn parse_time(raw: &str) -> rusqlite::Result<Time> {
+    let mut parts = raw.splitn(3, '-').map(|p| p.parse::<usize>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) => Ok(Time { year, month, day }),
+        _ => Err(rusqlite::Error::InvalidColumnType(
+            0,
+            raw.to_string(),
+            rusqlite::types::Type::Text,
+        )),
+    }
+}
+
+fn parse_datetime(raw: &str) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| {
+            rusqlite::Error::InvalidColumnType(0, raw.to_string(), rusqlite::types::Type::Text)
+        })
+}
+
+fn parse_roster_change(
+    kind: &str,
+    role: Role,
+    previous_role: Option<Role>,
+) -> rusqlite::Result<RosterChange> {
+    match (kind, previous_role) {
+        ("joined", _) => Ok(RosterChange::Joined { role }),
+        ("left", _) => Ok(RosterChange::Left { role }),
+        ("role_changed", Some(from)) => Ok(RosterChange::RoleChanged { from, to: role }),
+        _ => Err(rusqlite::Error::InvalidColumnType(
+            0,
+            kind.to_string(),
+            rusqlite::types::Type::Text,
+        )),
+    }
+}
+
+fn write_attacks(
+    tx: &rusqlite::Transaction<'_>,
+    sql: &str,
+    clan: &str,
+    season: &str,
+    war: &dyn rusqlite::ToSql,
+    player: &PlayerTag,
+    attacks: &[WarAttack],
+) -> rusqlite::Result<()> {
+    let mut stmt = tx.prepare_cached(sql)?;
+    for (position, attack) in attacks.iter().enumerate() {
+        stmt.execute(params![
+            clan,
+            season,
+            war,
+            player.0,
+            position as i64,
+            attack.destruction as i64,
+            attack.stars as i64,
+            attack.duration as i64,
+            attack.order.map(|o| o as i64),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replaces all the Data of the Season
+fn write_season(
+    tx: &rusqlite::Transaction<'_>,
+    clan: &ClanTag,
+    season: &Season,
+    stats: &ClanStorage,
+) -> rusqlite::Result<()> {
+    let clan = clan.0.as_str();
+    let season = season_key(season);
+
+    for table in SEASON_TABLES {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE clan = ?1 AND season = ?2", table),
+            params![clan, season],
+        )?;
+    }
+    tx.execute(
+        "INSERT OR IGNORE INTO seasons (clan, season) VALUES (?1, ?2)",
+        params![clan, season],
+    )?;
+
+    for (player, name) in stats.player_names.iter() {
+        tx.execute(
+            "INSERT INTO player_names (clan, season, player, name) VALUES (?1, ?2, ?3, ?4)",
+            params![clan, season, player.0, name],
+        )?;
+    }
+
+    for (player, role) in stats.roster.members.iter() {
+        tx.execute(
+            "INSERT INTO roster_members (clan, season, player, role) VALUES (?1, ?2, ?3, ?4)",
+            params![clan, season, player.0, role.as_str()],
+        )?;
+    }
+    for (position, event) in stats.roster.events.iter().enumerate() {
+        let (kind, role, previous_role) = match event.change {
+            RosterChange::Joined { role } => ("joined", role, None),
+            RosterChange::Left { role } => ("left", role, None),
+            RosterChange::RoleChanged { from, to } => ("role_changed", to, Some(from)),
+        };
+        tx.execute(
+            "INSERT INTO roster_events (clan, season, position, time, player, kind, role, previous_role) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                clan,
+                season,
+                position as i64,
+                event.time.to_rfc3339(),
+                event.player.0,
+                kind,
+                role.as_str(),
+                previous_role.map(|r| r.as_str())
+            ],
+        )?;
+    }
+
+    for (round, war) in stats.cwl.wars.iter().enumerate() {
+        let round = round as i64;
+        tx.execute(
+            "INSERT INTO cwl_rounds (clan, season, round) VALUES (?1, ?2, ?3)",
+            params![clan, season, round],
+        )?;
+
+        for (player, member) in war.members.iter() {
+            tx.execute(
+                "INSERT INTO cwl_members (clan, season, round, player) VALUES (?1, ?2, ?3, ?4)",
+                params![clan, season, round, player.0],
+            )?;
+            write_attacks(
+                tx,
+                "INSERT INTO cwl_attacks (clan, season, round, player, position, destruction, stars, duration, attack_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                clan,
+                &season,
+                &round,
+                player,
+                &member.attacks,
+            )?;
+        }
+    }
+
+    for war in stats.wars.values() {
+        let start_time = war.start_time.to_string();
+        tx.execute(
+            "INSERT INTO wars (clan, season, start_time, end_time, finished) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                clan,
+                season,
+                start_time,
+                war.end_time.map(|t| t.to_string()),
+                war.finished
+            ],
+        )?;
+
+        if let Some(summary) = war.summary.as_ref() {
+            tx.execute(
+                "INSERT INTO war_summaries (clan, season, start_time, summary_only, result, team_size, stars, destruction, opponent) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    clan,
+                    season,
+                    start_time,
+                    war.summary_only,
+                    summary.result,
+                    summary.team_size as i64,
+                    summary.stars as i64,
+                    summary.destruction,
+                    summary.opponent.as_ref().map(|o| o.0.as_str())
+                ],
+            )?;
+        }
+
+        for (player, member) in war.members.iter() {
+            tx.execute(
+                "INSERT INTO war_members (clan, season, start_time, player) VALUES (?1, ?2, ?3, ?4)",
+                params![clan, season, start_time, player.0],
+            )?;
+            write_attacks(
+                tx,
+                "INSERT INTO war_attacks (clan, season, start_time, player, position, destruction, stars, duration, attack_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                clan,
+                &season,
+                &start_time,
+                player,
+                &member.attacks,
+            )?;
+        }
+    }
+
+    for raid in stats.raid_weekend.values() {
+        let start_time = raid.start_time.to_string();
+        tx.execute(
+            "INSERT INTO raid_weekends (clan, season, start_time) VALUES (?1, ?2, ?3)",
+            params![clan, season, start_time],
+        )?;
+
+        for (player, member) in raid.members.iter() {
+            tx.execute(
+                "INSERT INTO raid_members (clan, season, start_time, player, looted) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![clan, season, start_time, player.0, member.looted as i64],
+            )?;
+        }
+    }
+
+    for (player, games) in stats.games.iter() {
+        tx.execute(
+            "INSERT INTO games (clan, season, player, start_score, end_score, late, monthly_baseline) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                clan,
+                season,
+                player.0,
+                games.start_score.map(|s| s as i64),
+                games.end_score as i64,
+                games.late,
+                games.monthly_baseline
+            ],
+        )?;
+    }
+
+    for (player, donations) in stats.donations.iter() {
+        tx.execute(
+            "INSERT INTO donations (clan, season, player, donated, received, last_donations, last_received) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                clan,
+                season,
+                player.0,
+                donations.donated as i64,
+                donations.received as i64,
+                donations.last_donations as i64,
+                donations.last_received as i64
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The Attacks of the Players keyed by Clan, Season, War and Player
+type Attacks = HashMap<(String, String, String, PlayerTag), Vec<WarAttack>>;
+
+fn read_attacks(connection: &Connection, sql: &str) -> rusqlite::Result<Attacks> {
+    let mut attacks: HashMap<_, Vec<_>> = HashMap::new();
+
+    let mut stmt = connection.prepare(sql)?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let key = (
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            PlayerTag(row.get(3)?),
+        );
+        attacks.entry(key).or_default().push(WarAttack {
+            destruction: row.get::<_, i64>(4)? as usize,
+            stars: row.get::<_, i64>(5)? as usize,
+            duration: row.get::<_, i64>(6)? as usize,
+            order: row.get::<_, Option<i64>>(7)?.map(|o| o as usize),
+        });
+    }
+
+    Ok(attacks)
+}
+
+fn read_storage(connection: &Connection) -> rusqlite::Result<Storage> {
+    let mut storage = Storage::empty();
+
+    let mut stmt = connection.prepare("SELECT tag FROM clans")?;
+    for tag in stmt.query_map([], |row| row.get::<_, String>(0))? {
+        storage.register_clan(ClanTag(tag?));
+    }
+
+    let mut seasons: BTreeMap<(String, String), ClanStorage> = BTreeMap::new();
+    let mut stmt = connection.prepare("SELECT clan, season FROM seasons")?;
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        seasons.insert(row?, ClanStorage::default());
+    }
+
+    let mut stmt = connection.prepare("SELECT clan, season, player, name FROM player_names")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            stats
+                .player_names
+                .insert(PlayerTag(row.get(2)?), row.get(3)?);
+        }
+    }
+
+    let mut stmt = connection.prepare("SELECT clan, season, player, role FROM roster_members")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            let role: String = row.get(3)?;
+            stats.roster.members.insert(
+                PlayerTag(row.get(2)?),
+                role.parse().unwrap_or(Role::Unknown),
+            );
+        }
+    }
+
+    let mut stmt = connection.prepare(
+        "SELECT clan, season, time, player, kind, role, previous_role FROM roster_events ORDER BY position",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            let role: String = row.get(5)?;
+            let previous_role: Option<String> = row.get(6)?;
+            let change = parse_roster_change(
+                &row.get::<_, String>(4)?,
+                role.parse().unwrap_or(Role::Unknown),
+                previous_role.map(|r| r.parse().unwrap_or(Role::Unknown)),
+            )?;
+
+            stats.roster.events.push(RosterEvent {
+                time: parse_datetime(&row.get::<_, String>(2)?)?,
+                player: PlayerTag(row.get(3)?),
+                change,
+            });
+        }
+    }
+
+    let mut stmt =
+        connection.prepare("SELECT clan, season, round FROM cwl_rounds ORDER BY round")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            let round = row.get::<_, i64>(2)? as usize;
+            stats.cwl.wars.resize_with(round + 1, || CwlWarStats {
+                members: HashMap::new(),
+            });
+        }
+    }
+
+    let mut cwl_attacks = read_attacks(
+        connection,
+        "SELECT clan, season, CAST(round AS TEXT), player, destruction, stars, duration, attack_order FROM cwl_attacks ORDER BY position",
+    )?;
+    let mut stmt = connection.prepare("SELECT clan, season, round, player FROM cwl_members")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let (clan, season, round, player): (String, String, i64, String) =
+            (row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+        let player = PlayerTag(player);
+        let attacks = cwl_attacks
+            .remove(&(
+                clan.clone(),
+                season.clone(),
+                round.to_string(),
+                player.clone(),
+            ))
+            .unwrap_or_default();
+
+        if let Some(war) = seasons
+            .get_mut(&(clan, season))
+            .and_then(|s| s.cwl.wars.get_mut(round as usize))
+        {
+            war.members.insert(player, MemberWarStats { attacks });
+        }
+    }
+
+    let mut stmt =
+        connection.prepare("SELECT clan, season, start_time, end_time, finished FROM wars")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            let start_time = parse_time(&row.get::<_, String>(2)?)?;
+            let end_time = row
+                .get::<_, Option<String>>(3)?
+                .map(|t| parse_time(&t))
+                .transpose()?;
+
+            stats.wars.insert(
+                start_time,
+                WarStats {
+                    start_time,
+                    end_time,
+                    finished: row.get(4)?,
+                    members: HashMap::new(),
+                    summary: None,
+                    summary_only: false,
+                },
+            );
+        }
+    }
+
+    let mut stmt = connection.prepare(
+        "SELECT clan, season, start_time, summary_only, result, team_size, stars, destruction, opponent FROM war_summaries",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let start_time = parse_time(&row.get::<_, String>(2)?)?;
+        if let Some(war) = seasons
+            .get_mut(&(row.get(0)?, row.get(1)?))
+            .and_then(|s| s.wars.get_mut(&start_time))
+        {
+            war.summary_only = row.get(3)?;
+            war.summary = Some(WarSummary {
+                result: row.get(4)?,
+                team_size: row.get::<_, i64>(5)? as usize,
+                stars: row.get::<_, i64>(6)? as usize,
+                destruction: row.get(7)?,
+                opponent: row.get::<_, Option<String>>(8)?.map(ClanTag),
+            });
+        }
+    }
+
+    let mut war_attacks = read_attacks(
+        connection,
+        "SELECT clan, season, start_time, player, destruction, stars, duration, attack_order FROM war_attacks ORDER BY position",
+    )?;
+    let mut stmt =
+        connection.prepare("SELECT clan, season, start_time, player FROM war_members")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let (clan, season, start_time, player): (String, String, String, String) =
+            (row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+        let player = PlayerTag(player);
+        let attacks = war_attacks
+            .remove(&(
+                clan.clone(),
+                season.clone(),
+                start_time.clone(),
+                player.clone(),
+            ))
+            .unwrap_or_default();
+
+        let start_time = parse_time(&start_time)?;
+        if let Some(war) = seasons
+            .get_mut(&(clan, season))
+            .and_then(|s| s.wars.get_mut(&start_time))
+        {
+            war.members.insert(player, MemberWarStats { attacks });
+        }
+    }
+
+    let mut stmt = connection.prepare("SELECT clan, season, start_time FROM raid_weekends")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            let start_time = parse_time(&row.get::<_, String>(2)?)?;
+            stats.raid_weekend.insert(
+                start_time,
+                RaidWeekendStats {
+                    start_time,
+                    members: HashMap::new(),
+                },
+            );
+        }
+    }
+
+    let mut stmt =
+        connection.prepare("SELECT clan, season, start_time, player, looted FROM raid_members")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let start_time = parse_time(&row.get::<_, String>(2)?)?;
+        if let Some(raid) = seasons
+            .get_mut(&(row.get(0)?, row.get(1)?))
+            .and_then(|s| s.raid_weekend.get_mut(&start_time))
+        {
+            raid.members.insert(
+                PlayerTag(row.get(3)?),
+                RaidMember {
+                    looted: row.get::<_, i64>(4)? as usize,
+                },
+            );
+        }
+    }
+
+    let mut stmt = connection.prepare(
+        "SELECT clan, season, player, start_score, end_score, late, monthly_baseline FROM games",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            stats.games.insert(
+                PlayerTag(row.get(2)?),
+                PlayerGamesStats {
+                    start_score: row.get::<_, Option<i64>>(3)?.map(|s| s as usize),
+                    end_score: row.get::<_, i64>(4)? as usize,
+                    late: row.get(5)?,
+                    monthly_baseline: row.get(6)?,
+                },
+            );
+        }
+    }
+
+    let mut stmt = connection.prepare(
+        "SELECT clan, season, player, donated, received, last_donations, last_received FROM donations",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(stats) = seasons.get_mut(&(row.get(0)?, row.get(1)?)) {
+            stats.donations.insert(
+                PlayerTag(row.get(2)?),
+                PlayerDonationStats {
+                    donated: row.get::<_, i64>(3)? as usize,
+                    received: row.get::<_, i64>(4)? as usize,
+                    last_donations: row.get::<_, i64>(5)? as usize,
+                    last_received: row.get::<_, i64>(6)? as usize,
+                },
+            );
+        }
+    }
+
+    let mut stmt = connection.prepare("SELECT player, user FROM links")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        storage
+            .links
+            .link(row.get::<_, i64>(1)? as u64, PlayerTag(row.get(0)?));
+    }
+
+    for ((clan, season), stats) in seasons {
+        let season: Season = match season.parse() {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Invalid Season {:?}: {}", season, e);
+                continue;
+            }
+        };
+
+        storage
+            .clans
+            .entry(ClanTag(clan))
+            .or_default()
+            .insert(season, stats);
+    }
+
+    Ok(storage)
+}
+
+impl StorageBackend for SqliteStorage {
+    #[tracing::instrument(skip(self, content))]
+    fn write(
+        &mut self,
+        content: Vec<u8>,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), StorageError>> + Send + 'static>,
+    > {
+        let path = self.path.clone();
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            tracing::trace!("Storing to SQLite");
+
+            let document = serde_json::from_slice(&content).map_err(StorageError::Deserialize)?;
+            let saved_at = migration::saved_at(&document);
+            let storage: Storage = serde_json::from_value(migration::upgrade(document)?)
+                .map_err(StorageError::Deserialize)?;
+
+            tokio::task::spawn_blocking(move || {
+                state.lock().unwrap().write(&path, &storage, saved_at)
+            })
+            .await
+            .expect("SQLite Write panicked")
+        })
+    }
+
+    fn load(
+        &mut self,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<u8>, StorageError>> + Send + 'static>,
+    > {
+        let path = self.path.clone();
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let (storage, saved_at) =
+                tokio::task::spawn_blocking(move || state.lock().unwrap().load(&path))
+                    .await
+                    .expect("SQLite Load panicked")?;
+
+            serde_json::to_vec(&Envelope {
+                version: CURRENT_VERSION,
+                saved_at,
+                data: &storage,
+            })
+            .map_err(StorageError::Serialize)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let dir = std::env::temp_dir().join(format!("gold-pass-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.sqlite");
+
+        let mut backend = SqliteStorage::new(&path);
+        assert!(matches!(
+            Storage::load(&mut backend).await,
+            Err(StorageError::NotFound)
+        ));
+
+        let document =
+            serde_json::from_str(include_str!("../../fixtures/storage/v1.json")).unwrap();
+        let mut storage: Storage =
+            serde_json::from_value(migration::upgrade(document).unwrap()).unwrap();
+
+        // A War only known from the War-Log
+        let start_time: Time =
+            serde_json::from_value(serde_json::json!("20230805T000000.000Z")).unwrap();
+        storage
+            .get_mut(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap()
+            .wars
+            .insert(
+                start_time,
+                WarStats {
+                    start_time,
+                    end_time: None,
+                    finished: true,
+                    members: HashMap::new(),
+                    summary: Some(WarSummary {
+                        result: Some("win".to_string()),
+                        team_size: 15,
+                        stars: 40,
+                        destruction: 91.5,
+                        opponent: Some(ClanTag("#8YQ2LVG0".to_string())),
+                    }),
+                    summary_only: true,
+                },
+            );
+        storage
+            .get_mut(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap()
+            .donations
+            .insert(
+                PlayerTag("#P2Y8QJ0L".to_string()),
+                PlayerDonationStats {
+                    donated: 420,
+                    received: 80,
+                    last_donations: 1020,
+                    last_received: 310,
+                },
+            );
+        let clan_stats = storage
+            .get_mut(
+                &ClanTag("#2L99VLJ9P".to_string()),
+                &Season {
+                    year: 2023,
+                    month: 8,
+                },
+            )
+            .unwrap();
+        clan_stats.roster.members = [
+            (PlayerTag("#P2Y8QJ0L".to_string()), Role::CoLeader),
+            (PlayerTag("#9YQ2LC8R".to_string()), Role::Member),
+        ]
+        .into_iter()
+        .collect();
+        clan_stats.roster.events = vec![
+            RosterEvent {
+                time: "2023-08-03T18:30:00Z".parse().unwrap(),
+                player: PlayerTag("#9YQ2LC8R".to_string()),
+                change: RosterChange::Joined { role: Role::Member },
+            },
+            RosterEvent {
+                time: "2023-08-09T07:00:00Z".parse().unwrap(),
+                player: PlayerTag("#P2Y8QJ0L".to_string()),
+                change: RosterChange::RoleChanged {
+                    from: Role::Admin,
+                    to: Role::CoLeader,
+                },
+            },
+        ];
+        storage.save(&mut backend).await.unwrap();
+
+        // A fresh Backend has to load everything from the Database
+        let mut backend = SqliteStorage::new(&path);
+        let loaded = Storage::load(&mut backend).await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&storage).unwrap(),
+            serde_json::to_value(&loaded).unwrap()
+        );
+
+        // The Time of the Save is kept, so Replicas can be compared
+        let document = serde_json::from_slice(&backend.load().await.unwrap()).unwrap();
+        assert!(migration::saved_at(&document).is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -64,6 +64,27 @@ impl Serialize for ClanTag {
     }
 }
 
+/// Normalizes a user supplied Tag, the leading `#` is optional and the Tag is converted to
+/// uppercase
+fn normalize_tag(s: &str) -> Option<String> {
+    let raw = s.trim();
+    let raw = raw.strip_prefix('#').unwrap_or(raw);
+
+    if raw.is_empty() || !raw.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    Some(format!("#{}", raw.to_ascii_uppercase()))
+}
+
+impl std::str::FromStr for ClanTag {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        normalize_tag(s).map(Self).ok_or("Invalid Clan-Tag")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct WarTag(pub String);
 
@@ -99,6 +120,14 @@ impl<'de> Deserialize<'de> for PlayerTag {
     }
 }
 
+impl std::str::FromStr for PlayerTag {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        normalize_tag(s).map(Self).ok_or("Invalid Player-Tag")
+    }
+}
+
 impl Serialize for PlayerTag {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -126,4 +155,19 @@ mod tests {
                 .inner,
         );
     }
+
+    #[test]
+    fn parse_clantag() {
+        assert_eq!(Ok(ClanTag("#2L99VLJ9P".to_string())), "#2L99VLJ9P".parse());
+        assert_eq!(Ok(ClanTag("#2L99VLJ9P".to_string())), "2l99vlj9p".parse());
+        assert!("".parse::<ClanTag>().is_err());
+        assert!("#".parse::<ClanTag>().is_err());
+        assert!("#2L9 9".parse::<ClanTag>().is_err());
+    }
+
+    #[test]
+    fn parse_playertag() {
+        assert_eq!(Ok(PlayerTag("#P2Q8Y".to_string())), " #p2q8y ".parse());
+        assert!("Some Name".parse::<PlayerTag>().is_err());
+    }
 }
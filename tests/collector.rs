@@ -0,0 +1,266 @@
+//! Runs the Collectors against the Mock-API serving the recorded Responses in `fixtures/api`
+
+use std::{net::SocketAddr, sync::Mutex};
+
+use chrono::TimeZone;
+use futures::TryStreamExt;
+
+use gold_pass_bot::{
+    BackfillReport, ClanGamesEvent, ClanTag, Client, EventPhase, PlayerDonationStats,
+    PlayerGamesStats, PlayerTag, Role, RosterChange, Season, Storage, Time, WarKind,
+};
+
+fn setup() -> (Client, ClanTag, Mutex<Storage>) {
+    let addr = gold_pass_bot::mockapi::spawn(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/api"),
+        SocketAddr::from(([127, 0, 0, 1], 0)),
+    );
+    let client = Client::new("test-key").with_base_url(format!("http://{}", addr));
+
+    let clan = ClanTag("#2L99VLJ9P".to_string());
+    let mut storage = Storage::empty();
+    storage.register_clan(clan.clone());
+
+    (client, clan, Mutex::new(storage))
+}
+
+fn season(year: usize, month: usize) -> Season {
+    Season { year, month }
+}
+
+#[tokio::test]
+async fn collect_all() {
+    let (client, clan, storage) = setup();
+    let alice = PlayerTag("#P2Y8QJ0L".to_string());
+    let bob = PlayerTag("#Q8RRJ0V2".to_string());
+
+    // Alice was already tracked in the previous Season
+    storage
+        .lock()
+        .unwrap()
+        .get_mut(&clan, &Season::current().previous())
+        .unwrap()
+        .donations
+        .insert(alice.clone(), PlayerDonationStats::new(1000, 40));
+    let carol = PlayerTag("#LQ9PV0GC".to_string());
+    storage
+        .lock()
+        .unwrap()
+        .get_mut(&clan, &Season::current().previous())
+        .unwrap()
+        .roster
+        .members = [(alice.clone(), Role::Admin), (carol.clone(), Role::Member)]
+        .into_iter()
+        .collect();
+
+    gold_pass_bot::update_names(&client, &clan, &storage)
+        .await
+        .unwrap();
+    let open_war = gold_pass_bot::update_war(&client, &clan, &storage)
+        .await
+        .unwrap()
+        .unwrap();
+    gold_pass_bot::reconcile_wars(&client, &clan, &storage)
+        .await
+        .unwrap();
+    let open_cwl = gold_pass_bot::update_cwl(&client, &clan, &storage)
+        .await
+        .unwrap();
+    // The Clan Games are running, Bob was already seen before they started
+    let now = chrono::Utc::now();
+    let event = ClanGamesEvent {
+        start: now - chrono::Duration::hours(1),
+        end: now + chrono::Duration::hours(1),
+        max_points: 4000,
+    };
+    storage
+        .lock()
+        .unwrap()
+        .get_mut(&clan, &Season::from(Time::from(event.start)))
+        .unwrap()
+        .games
+        .insert(
+            bob.clone(),
+            PlayerGamesStats::new(27000, EventPhase::Before),
+        );
+    gold_pass_bot::update_clan_games(&client, &clan, &storage, &event)
+        .await
+        .unwrap();
+    gold_pass_bot::update_raids(&client, &clan, &storage)
+        .await
+        .unwrap();
+
+    // Bob only used one of his two Attacks in the running War, the CWL War already ended
+    assert_eq!(WarKind::Regular, open_war.kind);
+    assert_eq!(1, open_war.missing.len());
+    assert_eq!(bob, open_war.missing[0].tag);
+    assert_eq!(1, open_war.missing[0].remaining);
+    assert!(open_cwl.is_empty());
+
+    let storage = storage.into_inner().unwrap();
+
+    let games = &storage
+        .get(&clan, &Season::from(Time::from(event.start)))
+        .unwrap()
+        .games;
+    assert_eq!(0, games[&alice].score());
+    assert!(games[&alice].late);
+    // Bob earned 4500 Points, but only the maximum of the Event counts
+    assert_eq!(4000, games[&bob].score());
+    assert!(!games[&bob].late);
+
+    let current = storage.get(&clan, &Season::current()).unwrap();
+    assert_eq!(Some(&"Alice".to_string()), current.player_names.get(&alice));
+    assert_eq!(205, current.player_summary(&alice).donations);
+    assert_eq!(0, current.player_summary(&bob).donations);
+    assert_eq!(512, current.donations[&bob].last_received);
+
+    // The Roster continues from the previous Season
+    let changes: Vec<_> = current
+        .roster
+        .events
+        .iter()
+        .map(|e| (&e.player, &e.change))
+        .collect();
+    assert_eq!(
+        vec![
+            (&carol, &RosterChange::Left { role: Role::Member }),
+            (
+                &alice,
+                &RosterChange::RoleChanged {
+                    from: Role::Admin,
+                    to: Role::Leader
+                }
+            ),
+            (&bob, &RosterChange::Joined { role: Role::Member }),
+        ],
+        changes
+    );
+
+    let august = storage.get(&clan, &season(2023, 8)).unwrap();
+    let summary = august.player_summary(&alice);
+    assert_eq!(3, summary.cwl_stars);
+    assert_eq!(5, summary.war_stars);
+    assert_eq!(30000, summary.raid_loot);
+
+    let war = august.wars.values().find(|w| !w.summary_only).unwrap();
+    assert!(!war.finished);
+    assert_eq!(Some(3), war.members[&alice].attacks[1].order);
+}
+
+#[tokio::test]
+async fn reconcile_unrecorded_war() {
+    let (client, clan, storage) = setup();
+
+    // The War in the War-Log was never recorded by the Bot
+    gold_pass_bot::reconcile_wars(&client, &clan, &storage)
+        .await
+        .unwrap();
+    gold_pass_bot::reconcile_wars(&client, &clan, &storage)
+        .await
+        .unwrap();
+
+    let storage = storage.into_inner().unwrap();
+    let august = storage.get(&clan, &season(2023, 8)).unwrap();
+    assert_eq!(1, august.wars.len());
+
+    let war = august.wars.values().next().unwrap();
+    assert!(war.finished);
+    assert!(war.summary_only);
+    assert!(war.members.is_empty());
+    assert_eq!(Some("win"), war.summary.as_ref().unwrap().result.as_deref());
+    let end = chrono::Utc.with_ymd_and_hms(2023, 8, 12, 7, 0, 0).unwrap();
+    assert_eq!(Some(Time::from(end)), war.end_time);
+}
+
+#[tokio::test]
+async fn missing_fixture() {
+    let (client, _, storage) = setup();
+    let unknown = ClanTag("#UNKNOWN".to_string());
+    storage.lock().unwrap().register_clan(unknown.clone());
+
+    assert!(gold_pass_bot::update_war(&client, &unknown, &storage)
+        .await
+        .is_err());
+    assert!(client.clan_info(&unknown).await.is_err());
+}
+
+#[tokio::test]
+async fn verify_token() {
+    let (client, _, _) = setup();
+    let alice = PlayerTag("#P2Y8QJ0L".to_string());
+
+    assert!(client.verify_player_token(&alice, "abc123").await.unwrap());
+    assert!(client
+        .verify_player_token(&PlayerTag("#UNKNOWN".to_string()), "abc123")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn paginate_war_log() {
+    let (client, clan, _) = setup();
+
+    let entries: Vec<_> = client.war().all_logs(&clan).try_collect().await.unwrap();
+    let end_times: Vec<_> = entries.iter().map(|e| e.end_time.as_str()).collect();
+    assert_eq!(
+        vec!["20230812T070000.000Z", "20230805T070000.000Z"],
+        end_times
+    );
+
+    // The first Page is still loaded on its own
+    let first = client.war().logs(&clan).await.unwrap();
+    assert_eq!(1, first.items.len());
+    assert_eq!(Some("eyJwb3MiOjF9"), first.paging.cursors.after.as_deref());
+}
+
+#[tokio::test]
+async fn backfill() {
+    let (client, clan, storage) = setup();
+    let alice = PlayerTag("#P2Y8QJ0L".to_string());
+
+    gold_pass_bot::update_war(&client, &clan, &storage)
+        .await
+        .unwrap();
+
+    let report = gold_pass_bot::backfill(&client, &clan, &storage)
+        .await
+        .unwrap();
+    assert_eq!(
+        BackfillReport {
+            wars_added: 2,
+            wars_summarized: 0,
+            raids_added: 1,
+            skipped: 0,
+        },
+        report
+    );
+
+    // Backfilling again does not change anything
+    let report = gold_pass_bot::backfill(&client, &clan, &storage)
+        .await
+        .unwrap();
+    assert_eq!(BackfillReport::default(), report);
+
+    let storage = storage.into_inner().unwrap();
+    let august = storage.get(&clan, &season(2023, 8)).unwrap();
+    assert_eq!(3, august.wars.len());
+
+    // The running War is still recorded with its Attacks
+    let (summarized, recorded): (Vec<_>, Vec<_>) =
+        august.wars.values().partition(|w| w.summary_only);
+    assert_eq!(1, recorded.len());
+    assert!(recorded[0].summary.is_none());
+    assert!(!recorded[0].members.is_empty());
+
+    let results: Vec<_> = summarized
+        .iter()
+        .map(|w| w.summary.as_ref().unwrap().result.as_deref())
+        .collect();
+    assert_eq!(vec![Some("lose"), Some("win")], results);
+    assert!(summarized
+        .iter()
+        .all(|w| w.finished && w.members.is_empty()));
+
+    assert_eq!(30000, august.player_summary(&alice).raid_loot);
+}